@@ -85,6 +85,7 @@ exports.XMLHttpRequest = function() {
 
   // Event listeners
   var listeners = {};
+  var uploadListeners = {};
 
   /**
    * Constants
@@ -117,6 +118,17 @@ exports.XMLHttpRequest = function() {
   // credentials such as cookies or authorization headers
   this.withCredentials = false;
 
+  // Only supports progress events, which are dispatched once the whole
+  // request body has been sent.
+  this.upload = {
+    addEventListener: function(event, callback) {
+      addListener(uploadListeners, event, callback);
+    },
+    removeEventListener: function(event, callback) {
+      removeListener(uploadListeners, event, callback);
+    }
+  };
+
   /**
    * Private methods
    */
@@ -432,14 +444,23 @@ exports.XMLHttpRequest = function() {
         setState(self.HEADERS_RECEIVED);
         self.status = response.statusCode;
 
+        var total = parseInt(response.headers["content-length"], 10);
+        var loaded = 0;
+
         response.on("data", function(chunk) {
           // Make sure there's some data
           if (chunk) {
             self.responseText += chunk;
+            loaded += Buffer.byteLength(chunk);
           }
           // Don't emit state changes if the connection has been aborted.
           if (sendFlag) {
             setState(self.LOADING);
+            self.dispatchEvent("progress", {
+              lengthComputable: !isNaN(total),
+              loaded: loaded,
+              total: isNaN(total) ? 0 : total
+            });
           }
         });
 
@@ -468,6 +489,16 @@ exports.XMLHttpRequest = function() {
 
       // Node 0.4 and later won't accept empty data. Make sure it's needed.
       if (data) {
+        var length = headers["Content-Length"];
+        request.on("finish", function() {
+          if (sendFlag) {
+            dispatchEventTo(uploadListeners, "progress", {
+              lengthComputable: true,
+              loaded: length,
+              total: length
+            });
+          }
+        });
         request.write(data);
       }
 
@@ -597,6 +628,10 @@ exports.XMLHttpRequest = function() {
    * Adds an event listener. Preferred method of binding to events.
    */
   this.addEventListener = function(event, callback) {
+    addListener(listeners, event, callback);
+  };
+
+  var addListener = function(listeners, event, callback) {
     if (!(event in listeners)) {
       listeners[event] = [];
     }
@@ -609,6 +644,10 @@ exports.XMLHttpRequest = function() {
    * Only works on the matching funciton, cannot be a copy.
    */
   this.removeEventListener = function(event, callback) {
+    removeListener(listeners, event, callback);
+  };
+
+  var removeListener = function(listeners, event, callback) {
     if (event in listeners) {
       // Filter will return a new array with the callback removed
       listeners[event] = listeners[event].filter(function(ev) {
@@ -620,13 +659,17 @@ exports.XMLHttpRequest = function() {
   /**
    * Dispatch any events, including both "on" methods and events attached using addEventListener.
    */
-  this.dispatchEvent = function(event) {
+  this.dispatchEvent = function(event, detail) {
     if (typeof self["on" + event] === "function") {
-      self["on" + event]();
+      self["on" + event](detail);
     }
+    dispatchEventTo(listeners, event, detail);
+  };
+
+  var dispatchEventTo = function(listeners, event, detail) {
     if (event in listeners) {
       for (var i = 0, len = listeners[event].length; i < len; i++) {
-        listeners[event][i].call(self);
+        listeners[event][i].call(self, detail);
       }
     }
   };
@@ -1,84 +1,203 @@
 
 const assert = require('assert');
+const fs = require('fs');
+const { performance } = require('perf_hooks');
 
 
-module.exports = function (generated, output) {
-    const { ports = [], flags, logs : expectedLogs = '' } = output;
+module.exports = function (generated, output, { recordFile, traceFile, generatedFile, notifyPortEvents = false } = {}) {
+    const { ports = [], flags, websocket } = output;
+    const trace = traceFile !== undefined ? tracer(traceFile) : () => {};
+    const portEventOccurred = notifyPortEvents ? portEventNotifier(flags.suite) : () => {};
     let actualLogs = ''
-    generated._debugLog = str => {
+    const actualPorts = [];
+    traceRequests(trace);
+    const debugLog = str => {
+        trace({ type: 'log', message: str });
         actualLogs += str + '\n';
-        // If we do not expect logs print them for debugging.
-        if (expectedLogs === '') {
-            console.log(str);
-        }
-    }
+    };
+    generated._debugLog = debugLog;
     if (!Object.prototype.hasOwnProperty.call(generated, '_another_elm')) {
         Date.now = () => 0;
+        if (generatedFile !== undefined) {
+            captureDebugLog(generatedFile, debugLog);
+        }
     }
     generated._randSeed = () => 0;
     const app = generated.Elm.Main.init(flags !== undefined ? { flags } : undefined);
+    const bridgedPorts = new Set();
+
+    if (websocket !== undefined) {
+        bridgeWebSocket(app, flags.suite.websocket, websocket);
+        bridgedPorts.add(websocket['send-port']);
+    }
+
+    // Events in an unordered group are listed in some valid order so the
+    // harness can treat them like any other events.
+    const flatPorts = ports.flatMap(entry => Array.isArray(entry) ? [entry] : entry.unordered);
+    record(app, flatPorts, actualPorts, portEventOccurred, bridgedPorts, trace);
+    process.on('exit', () => {
+        fs.writeFileSync(
+            recordFile,
+            JSON.stringify({ ports: actualPorts, logs: actualLogs }),
+        );
+    });
+}
+
+
+/**
+ * Record the port events of the app (elm-torture checks them once the app
+ * has finished).
+ *
+ * Subscription events are taken from the expectations and are sent after the
+ * same number of commands as they were before.
+ */
+function record(app, ports, actualPorts, portEventOccurred, bridgedPorts, trace) {
     let portEventIndex = 0;
 
-    function sendIfNextEventSubscription() {
-        if (portEventIndex < ports.length) {
-            const nextEvent = ports[portEventIndex];
-            const [nextType, nextPortName, nextData] = nextEvent;
-            if (nextType === "subscription") {
-                assert(
-                    app.ports[nextPortName] !== undefined,
-                    `Port event ${portEventIndex + 1} calls for sending ${nextData} to port ${nextPortName} but the app does not have such a port.`,
-                );
-                assert(
-                    app.ports[nextPortName].send !== undefined,
-                    `Port event ${portEventIndex + 1} calls for sending ${nextData} to port ${nextPortName} but that is a command port.`,
-                );
-                portEventIndex += 1;
-                app.ports[nextPortName].send(nextData);
+    function sendSubscriptions() {
+        while (portEventIndex < ports.length && ports[portEventIndex][0] === "subscription") {
+            const event = ports[portEventIndex];
+            const [, portName, data] = event;
+            portEventIndex += 1;
+            if (app.ports !== undefined && app.ports[portName] !== undefined && app.ports[portName].send !== undefined) {
+                actualPorts.push(event);
+                portEventOccurred(actualPorts.length);
+                trace({ type: 'subscription', port: portName, data });
+                app.ports[portName].send(data);
             }
         }
     }
 
     if (app.ports !== undefined) {
         for (const portName of Object.keys(app.ports)) {
-            if (app.ports[portName].subscribe !== undefined) {
+            if (app.ports[portName].subscribe !== undefined && !bridgedPorts.has(portName)) {
                 app.ports[portName].subscribe(data => {
-                    assert(
-                        portEventIndex < ports.length,
-                        `There should be exactly "${ports.length}" port events but this is event ${portEventIndex + 1}.`,
-                    );
-                    const [type, expectedName, expectedData] = ports[portEventIndex];
-                    assert(
-                        type === "command",
-                        `Port event ${portEventIndex} should be a ${type} but command ${portName} received (with value ${data}).`,
-                    );
-                    assert(
-                        expectedName === portName,
-                        `Port event ${portEventIndex} should be command ${expectedName} but command ${portName} received (with value ${data}).`,
-                    );
-                    assert.deepStrictEqual(
-                        data,
-                        expectedData,
-                        `Wrong data sent to port ${portName} during port event ${portEventIndex + 1}`,
-                    );
-                    portEventIndex += 1;
-                    sendIfNextEventSubscription();
+                    trace({ type: 'command', port: portName, data });
+                    actualPorts.push(["command", portName, data]);
+                    portEventOccurred(actualPorts.length);
+                    if (portEventIndex < ports.length && ports[portEventIndex][0] === "command") {
+                        portEventIndex += 1;
+                    }
+                    sendSubscriptions();
                 })
             }
         }
     }
 
-    sendIfNextEventSubscription();
+    sendSubscriptions();
+}
 
-    process.on('exit', () => {
-        assert.strictEqual(
-            portEventIndex, ports.length,
-            `There have been ${portEventIndex} port events but should have been exactly ${ports.length} port events.`,
-        );
-        assert.strictEqual(
-            actualLogs, expectedLogs,
-            `Unexpected logs`,
-        );
+
+/**
+ * Tell the mock servers how many port events have occurred so that they can
+ * release responses held until then.
+ */
+function portEventNotifier(suite) {
+    const servers = [suite, ...Object.values(suite.origins || {})]
+        .filter(({ url }) => url !== undefined);
+    return count => {
+        for (const { protocol, url } of servers) {
+            require(protocol === 'https://' ? 'https' : 'http')
+                .request(`${protocol}${url}/__elm-torture/port-events/${count}`, { method: 'POST' })
+                .on('response', res => res.resume())
+                .end();
+        }
+    };
+}
+
+
+/**
+ * Connect to the mock WebSocket server, sending messages from the app's send
+ * port to the server and messages from the server to the app's receive port.
+ */
+function bridgeWebSocket(app, { url }, { 'send-port': sendPort, 'receive-port': receivePort }) {
+    assert(
+        app.ports !== undefined && app.ports[sendPort] !== undefined && app.ports[sendPort].subscribe !== undefined,
+        `The app should have a command port ${sendPort} to send WebSocket messages with.`,
+    );
+    assert(
+        app.ports[receivePort] !== undefined && app.ports[receivePort].send !== undefined,
+        `The app should have a subscription port ${receivePort} to receive WebSocket messages with.`,
+    );
+    const WebSocket = require('./websocket.js');
+    const socket = new WebSocket(url);
+    let closed = false;
+    socket.on('message', message => app.ports[receivePort].send(message));
+    socket.on('close', () => { closed = true; });
+    socket.on('error', error => {
+        assert.fail(`The WebSocket connection to the mock server failed: ${error.message}`);
+    });
+    app.ports[sendPort].subscribe(message => {
+        const text = typeof message === 'string' ? message : JSON.stringify(message);
+        // The server closes the connection once the script has finished so
+        // anything sent afterwards is unexpected.
+        assert(!closed, `The app sent the WebSocket message ${JSON.stringify(text)} after the script had finished.`);
+        socket.send(text);
     });
+}
+
 
+/**
+ * The official stdlib's `Debug.log` writes with `console.log`. Record calls
+ * made directly by the generated code as logs and leave any others alone.
+ */
+function captureDebugLog(generatedFile, debugLog) {
+    const consoleLog = console.log;
+    console.log = function log(...args) {
+        const prepareStackTrace = Error.prepareStackTrace;
+        Error.prepareStackTrace = (_, stack) => stack;
+        const caller = {};
+        Error.captureStackTrace(caller, log);
+        const [frame] = caller.stack;
+        Error.prepareStackTrace = prepareStackTrace;
+        if (frame !== undefined && frame.getFileName() === generatedFile) {
+            debugLog(require('util').format(...args));
+        } else {
+            consoleLog.apply(console, args);
+        }
+    };
+}
+
+
+/**
+ * Record events as json lines in the trace file.
+ *
+ * Each event is appended as soon as it happens so that the trace is still
+ * available if the process is killed.
+ */
+function tracer(traceFile) {
+    const start = performance.now();
+    fs.writeFileSync(traceFile, '');
+    return event => {
+        fs.appendFileSync(
+            traceFile,
+            JSON.stringify({ time: performance.now() - start, ...event }) + '\n',
+        );
+    };
 }
 
+
+/**
+ * Trace every HTTP request the app makes.
+ */
+function traceRequests(trace) {
+    const { XMLHttpRequest } = global;
+    if (XMLHttpRequest === undefined) {
+        return;
+    }
+    global.XMLHttpRequest = function () {
+        const xhr = new XMLHttpRequest();
+        const { open, send } = xhr;
+        let method;
+        let url;
+        xhr.open = function (...args) {
+            [method, url] = args;
+            return open.apply(this, args);
+        };
+        xhr.send = function (...args) {
+            trace({ type: 'request', method, url });
+            return send.apply(this, args);
+        };
+        return xhr;
+    };
+}
@@ -0,0 +1,146 @@
+const crypto = require('crypto');
+const { EventEmitter } = require('events');
+
+const OPCODE_CONTINUATION = 0x0;
+const OPCODE_TEXT = 0x1;
+const OPCODE_BINARY = 0x2;
+const OPCODE_CLOSE = 0x8;
+const OPCODE_PING = 0x9;
+const OPCODE_PONG = 0xA;
+
+/**
+ * A minimal WebSocket client, enough to talk to the elm-torture mock server.
+ *
+ * Emits `open`, `message` (with the message as a string), `close` and
+ * `error`. Messages sent before the connection opens are queued.
+ */
+class WebSocket extends EventEmitter {
+    constructor(url) {
+        super();
+        this._socket = null;
+        this._queue = [];
+        this._buffer = Buffer.alloc(0);
+        this._fragments = [];
+
+        const httpUrl = url.replace(/^ws/, 'http');
+        const client = require(httpUrl.startsWith('https:') ? 'https' : 'http');
+        const request = client.request(httpUrl, {
+            headers: {
+                'Connection': 'Upgrade',
+                'Upgrade': 'websocket',
+                'Sec-WebSocket-Key': crypto.randomBytes(16).toString('base64'),
+                'Sec-WebSocket-Version': '13',
+            },
+        });
+        request.on('upgrade', (response, socket, head) => {
+            this._socket = socket;
+            socket.on('data', data => this._receive(data));
+            socket.on('close', () => this.emit('close'));
+            socket.on('error', error => this.emit('error', error));
+            this.emit('open');
+            for (const frame of this._queue) {
+                socket.write(frame);
+            }
+            this._queue = [];
+            this._receive(head);
+        });
+        request.on('response', response => {
+            this.emit('error', new Error(`WebSocket upgrade failed with status ${response.statusCode}`));
+        });
+        request.on('error', error => this.emit('error', error));
+        request.end();
+    }
+
+    send(message) {
+        this._write(OPCODE_TEXT, Buffer.from(message));
+    }
+
+    close() {
+        this._write(OPCODE_CLOSE, Buffer.alloc(0));
+    }
+
+    _write(opcode, payload) {
+        // Frames sent by a client must be masked.
+        const mask = crypto.randomBytes(4);
+        let header;
+        if (payload.length < 126) {
+            header = Buffer.from([0x80 | opcode, 0x80 | payload.length]);
+        } else if (payload.length < 0x10000) {
+            header = Buffer.alloc(4);
+            header[1] = 0x80 | 126;
+            header.writeUInt16BE(payload.length, 2);
+        } else {
+            header = Buffer.alloc(10);
+            header[1] = 0x80 | 127;
+            header.writeBigUInt64BE(BigInt(payload.length), 2);
+        }
+        header[0] = 0x80 | opcode;
+        const masked = Buffer.alloc(payload.length);
+        for (let i = 0; i < payload.length; i += 1) {
+            masked[i] = payload[i] ^ mask[i % 4];
+        }
+        const frame = Buffer.concat([header, mask, masked]);
+        if (this._socket === null) {
+            this._queue.push(frame);
+        } else {
+            this._socket.write(frame);
+        }
+    }
+
+    _receive(data) {
+        this._buffer = Buffer.concat([this._buffer, data]);
+        for (;;) {
+            if (this._buffer.length < 2) {
+                return;
+            }
+            const fin = (this._buffer[0] & 0x80) !== 0;
+            const opcode = this._buffer[0] & 0x0F;
+            let length = this._buffer[1] & 0x7F;
+            let offset = 2;
+            if (length === 126) {
+                if (this._buffer.length < 4) {
+                    return;
+                }
+                length = this._buffer.readUInt16BE(2);
+                offset = 4;
+            } else if (length === 127) {
+                if (this._buffer.length < 10) {
+                    return;
+                }
+                length = Number(this._buffer.readBigUInt64BE(2));
+                offset = 10;
+            }
+            // Frames sent by a server are never masked.
+            if (this._buffer.length < offset + length) {
+                return;
+            }
+            const payload = this._buffer.slice(offset, offset + length);
+            this._buffer = this._buffer.slice(offset + length);
+            this._frame(fin, opcode, payload);
+        }
+    }
+
+    _frame(fin, opcode, payload) {
+        switch (opcode) {
+            case OPCODE_CONTINUATION:
+            case OPCODE_TEXT:
+            case OPCODE_BINARY:
+                this._fragments.push(payload);
+                if (fin) {
+                    const message = Buffer.concat(this._fragments).toString();
+                    this._fragments = [];
+                    this.emit('message', message);
+                }
+                break;
+            case OPCODE_CLOSE:
+                this._write(OPCODE_CLOSE, Buffer.alloc(0));
+                this._socket.end();
+                break;
+            case OPCODE_PING:
+                this._write(OPCODE_PONG, payload);
+                break;
+        }
+    }
+}
+
+module.exports = WebSocket;
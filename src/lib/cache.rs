@@ -0,0 +1,131 @@
+use super::config::OptimizationLevel;
+use super::elm_home::ElmHomes;
+use super::scratch;
+use super::suite::ElmCompilerPath;
+use log::debug;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Bump this whenever the way that keys are computed changes.
+const KEY_VERSION: &[u8] = b"elm-torture compile cache 2";
+
+/// Compiled SSCCEs from previous runs of elm-torture.
+///
+/// Entries are keyed by a hash of everything that can change the output of
+/// the compiler: the suite's files (and any source directories outside of
+/// the suite), the contents of the compiler binary, the optimisation level
+/// and the fingerprint of the user's `ELM_HOME` (where the packages, and so
+/// the core libraries of wrapper script compilers, live). The suite's
+/// `output.json` only affects running the SSCCE and so is not part of the
+/// key. Deleting the directory discards every entry.
+#[derive(Debug)]
+pub struct CompileCache {
+    dir: PathBuf,
+    compilers: HashMap<PathBuf, Box<[u8]>>,
+    elm_home: String,
+}
+
+impl CompileCache {
+    pub fn new(
+        dir: PathBuf,
+        compilers: &[ElmCompilerPath],
+        elm_homes: &ElmHomes,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let compilers = compilers
+            .iter()
+            .map(|compiler| {
+                let mut hasher = Sha1::new();
+                hasher.update(fs::read(compiler.path())?);
+                Ok((
+                    compiler.path().to_path_buf(),
+                    hasher.finalize().as_slice().into(),
+                ))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            dir,
+            compilers,
+            elm_home: elm_homes.fingerprint().to_string(),
+        })
+    }
+
+    /// The entry for the output of compiling `suite`.
+    pub fn entry(
+        &self,
+        suite: &Path,
+        compiler: &ElmCompilerPath,
+        opt_level: OptimizationLevel,
+    ) -> io::Result<CacheEntry> {
+        let mut hasher = Sha1::new();
+        hasher.update(KEY_VERSION);
+        hasher.update(&self.compilers[compiler.path()]);
+        hasher.update(opt_level.id());
+        hasher.update(self.elm_home.as_bytes());
+        hash_tree(&mut hasher, suite, suite)?;
+        for dir in scratch::external_source_dirs(suite) {
+            hasher.update(dir.to_string_lossy().as_bytes());
+            hash_tree(&mut hasher, &dir, &dir)?;
+        }
+        Ok(CacheEntry {
+            dir: self.dir.clone(),
+            file: self.dir.join(format!("{:x}.js", hasher.finalize())),
+        })
+    }
+}
+
+fn hash_tree(hasher: &mut Sha1, root: &Path, dir: &Path) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        if entry.file_type()?.is_dir() {
+            if name != OsStr::new("elm-stuff") {
+                hash_tree(hasher, root, &path)?;
+            }
+        } else if !(dir == root && name == OsStr::new("output.json")) {
+            let contents = fs::read(&path)?;
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update((contents.len() as u64).to_le_bytes());
+            hasher.update(&contents);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct CacheEntry {
+    dir: PathBuf,
+    file: PathBuf,
+}
+
+impl CacheEntry {
+    /// Copy the cached output to `out_file`, returning false if there is
+    /// nothing cached.
+    pub fn load(&self, out_file: &Path) -> bool {
+        let hit = fs::copy(&self.file, out_file).is_ok();
+        debug!(
+            "Compile cache {} for {}",
+            if hit { "hit" } else { "miss" },
+            self.file.display()
+        );
+        hit
+    }
+
+    /// Cache the compiled output in `out_file`.
+    pub fn store(&self, out_file: &Path) -> io::Result<()> {
+        // Other workers may be loading this entry so it must never be seen
+        // partly written.
+        let partial = tempfile::NamedTempFile::new_in(&self.dir)?;
+        fs::copy(out_file, partial.path())?;
+        partial.persist(&self.file).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
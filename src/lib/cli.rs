@@ -1,4 +1,6 @@
 use crate::lib::config;
+use crate::lib::find_suites;
+use crate::lib::reporter::ReporterKind;
 use clap::Clap;
 use std::{ffi::OsStr, path::PathBuf};
 use std::{fs::File, path::Path};
@@ -23,11 +25,76 @@ struct Opts {
     )]
     suites: PathBuf,
 
+    #[clap(
+        long = "filter",
+        value_name = "PATTERN",
+        number_of_values = 1,
+        about = "Only run suites whose path (relative to the suites directory) matches PATTERN. A glob, or a regex if prefixed with `re:`."
+    )]
+    filters: Vec<find_suites::Pattern>,
+
+    #[clap(
+        long = "exclude",
+        value_name = "PATTERN",
+        number_of_values = 1,
+        about = "Do not run suites whose path (relative to the suites directory) matches PATTERN."
+    )]
+    excludes: Vec<find_suites::Pattern>,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        about = "Run the suites listed (one per line, relative to the suites directory) in FILE."
+    )]
+    suites_from: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "EXPRESSION",
+        about = "Only run suites whose tags match EXPRESSION, for example `http & !slow`."
+    )]
+    tags: Option<find_suites::TagExpression>,
+
+    #[clap(
+        long,
+        value_name = "EXPRESSION",
+        about = "Do not run suites whose tags match EXPRESSION."
+    )]
+    skip_tags: Option<find_suites::TagExpression>,
+
     #[clap(long, value_name = "FILE", about = "Dump the configuration to FILE.")]
     show_config: Option<PathBuf>,
 
     #[clap(long, about = "Stop running on the first failed suite.")]
     fail_fast: bool,
+
+    #[clap(
+        long,
+        about = "Record the port events and logs of each suite (with the first compiler and optimization level) into its output.json."
+    )]
+    bless: bool,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        about = "Write a JUnit XML report of the results to FILE."
+    )]
+    junit: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        about = "Write a json report of the results to FILE."
+    )]
+    report_json: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "REPORTER",
+        default_value = "pretty",
+        about = "How to report results: pretty, tap or github-actions."
+    )]
+    reporter: ReporterKind,
 }
 
 pub enum Task {
@@ -38,6 +105,11 @@ pub enum Task {
 pub struct Instructions {
     pub config: config::Config,
     pub fail_fast: bool,
+    pub bless: bool,
+    pub junit: Option<PathBuf>,
+    pub report_json: Option<PathBuf>,
+    pub reporter: ReporterKind,
+    pub selection: find_suites::Selection,
     pub task: Task,
 }
 
@@ -61,9 +133,18 @@ fn read_config_file(config_path: &OsStr) -> Result<config::Config, String> {
 pub fn get_cli_task() -> Instructions {
     let Opts {
         suites,
+        filters,
+        excludes,
+        suites_from,
+        tags,
+        skip_tags,
         config_from_file,
         fail_fast,
+        bless,
         show_config,
+        junit,
+        report_json,
+        reporter,
         config: config_from_cli,
         ..
     } = Opts::parse();
@@ -77,6 +158,17 @@ pub fn get_cli_task() -> Instructions {
     Instructions {
         config,
         fail_fast,
+        bless,
+        junit,
+        report_json,
+        reporter,
+        selection: find_suites::Selection {
+            filters,
+            excludes,
+            suites_from,
+            tags,
+            skip_tags,
+        },
         task: show_config.map_or_else(|| Task::RunSuites(suites), Task::DumpConfig),
     }
 }
@@ -90,6 +90,14 @@ pub struct Config {
     )]
     #[serde(skip_serializing_if = "Option::is_none")]
     run_timeout: Option<Duration>,
+    #[clap(
+        long,
+        value_name = "DURATION",
+        about = "Report compile failure if compiling an SSCCE takes more than <DURATION>.",
+        parse(try_from_str = humantime::parse_duration)
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compile_timeout: Option<Duration>,
 
     #[clap(
         long,
@@ -98,6 +106,14 @@ pub struct Config {
     )]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub out_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "DIRECTORY",
+        about = "Reuse SSCCEs compiled by previous runs (and cache newly compiled SSCCEs) in <DIRECTORY>."
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
 }
 
 impl Config {
@@ -118,7 +134,9 @@ impl Config {
             opt_levels: merge!(opt_levels),
             compiler_max_retries: merge!(compiler_max_retries),
             run_timeout: merge!(run_timeout),
+            compile_timeout: merge!(compile_timeout),
             out_dir: merge!(out_dir),
+            cache_dir: merge!(cache_dir),
         }
     }
 
@@ -148,6 +166,11 @@ impl Config {
     pub fn run_timeout(&self) -> Duration {
         self.run_timeout.unwrap_or_else(|| Duration::new(10, 0))
     }
+
+    pub fn compile_timeout(&self) -> Duration {
+        self.compile_timeout
+            .unwrap_or_else(|| Duration::new(300, 0))
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,144 @@
+use log::debug;
+use sha1::{Digest, Sha1};
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// A private `ELM_HOME` for each worker thread so that compilers can run
+/// concurrently without racing on the package cache.
+///
+/// Every home is seeded from a copy of the user's `ELM_HOME` (or `~/.elm`) so
+/// that packages need not be downloaded again. The homes are kept between
+/// runs that share an output directory and are rebuilt whenever the user's
+/// `ELM_HOME` changes. Only package sources are hard linked (compilers never
+/// write to them); every other file is copied so that nothing a compiler
+/// writes can reach the user's `ELM_HOME`.
+#[derive(Debug)]
+pub struct ElmHomes {
+    dir: PathBuf,
+    seed: Option<PathBuf>,
+    fingerprint: String,
+}
+
+impl ElmHomes {
+    pub fn new(dir: PathBuf) -> io::Result<Self> {
+        let user_home = env::var_os("ELM_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var_os("HOME")
+                    .or_else(|| env::var_os("USERPROFILE"))
+                    .map(|home| Path::new(&home).join(".elm"))
+            })
+            .filter(|user_home| user_home.is_dir());
+        let mut hasher = Sha1::new();
+        if let Some(user_home) = &user_home {
+            hasher.update(user_home.to_string_lossy().as_bytes());
+            fingerprint_tree(&mut hasher, user_home, user_home)?;
+        }
+        let fingerprint = format!("{:x}", hasher.finalize());
+
+        let fingerprint_file = dir.join("fingerprint");
+        if fs::read_to_string(&fingerprint_file).ok().as_deref() != Some(&fingerprint) {
+            if dir.exists() {
+                debug!("Removing the outdated elm homes in {}", dir.display());
+                fs::remove_dir_all(&dir)?;
+            }
+            fs::create_dir_all(&dir)?;
+            if let Some(user_home) = &user_home {
+                let seed = dir.join("seed");
+                debug!("Seeding {} from {}", seed.display(), user_home.display());
+                create_seeded(user_home, &seed)?;
+            }
+            fs::write(&fingerprint_file, &fingerprint)?;
+        }
+        let seed = user_home.map(|_| dir.join("seed"));
+        Ok(Self {
+            dir,
+            seed,
+            fingerprint,
+        })
+    }
+
+    /// A hash of the names, sizes and modification times of the files in the
+    /// user's `ELM_HOME`.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// The `ELM_HOME` of the current worker thread, creating it if needed.
+    ///
+    /// A worker thread only compiles one suite at a time so its home is
+    /// never shared.
+    pub fn for_current_thread(&self) -> io::Result<PathBuf> {
+        let name = match rayon::current_thread_index() {
+            Some(index) => index.to_string(),
+            None => "main".to_string(),
+        };
+        let home = self.dir.join(&name);
+        if !home.exists() {
+            match &self.seed {
+                Some(seed) => create_seeded(seed, &home)?,
+                None => fs::create_dir_all(&home)?,
+            }
+        }
+        Ok(home)
+    }
+}
+
+fn fingerprint_tree(hasher: &mut Sha1, root: &Path, dir: &Path) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            fingerprint_tree(hasher, root, &path)?;
+        } else {
+            let metadata = entry.metadata()?;
+            let modified = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update(metadata.len().to_le_bytes());
+            hasher.update(modified.as_nanos().to_le_bytes());
+        }
+    }
+    Ok(())
+}
+
+/// Create the directory `to` as a copy of `from`.
+fn create_seeded(from: &Path, to: &Path) -> io::Result<()> {
+    // Seed into a temporary directory so that a partly seeded directory is
+    // never used.
+    let mut partial = to.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let _ = fs::remove_dir_all(&partial);
+    fs::create_dir_all(&partial)?;
+    seed_dir(from, &partial)?;
+    fs::rename(&partial, to)
+}
+
+/// Whether `path` is a package source that compilers read but never write.
+fn is_package_source(path: &Path) -> bool {
+    matches!(path.extension().and_then(OsStr::to_str), Some("elm" | "js"))
+}
+
+fn seed_dir(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let to = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            fs::create_dir(&to)?;
+            seed_dir(&entry.path(), &to)?;
+        } else if !is_package_source(&entry.path()) || fs::hard_link(entry.path(), &to).is_err() {
+            fs::copy(entry.path(), &to)?;
+        }
+    }
+    Ok(())
+}
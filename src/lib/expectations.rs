@@ -0,0 +1,341 @@
+use super::matcher::ValuePattern;
+use super::suite::{PortEvent, PortName, PortType};
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::slice;
+
+/// A port event in a suite's config.
+///
+/// The arguments of commands are [`ValuePattern`]s. The harness sends the
+/// data of subscriptions as it is written so the data must match exactly.
+pub type ExpectedPortEvent = (PortType, PortName, ValuePattern);
+
+pub fn port_event_matches((e_type, e_name, e_arg): &ExpectedPortEvent, actual: &PortEvent) -> bool {
+    let (a_type, a_name, a_arg) = actual;
+    e_type == a_type
+        && e_name == a_name
+        && match e_type {
+            PortType::Command => e_arg.matches(&a_arg.0),
+            PortType::Subscription => *e_arg.raw() == a_arg.0,
+        }
+}
+
+/// An entry in the `ports` array of a suite's config.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged, try_from = "Value")]
+pub enum PortExpectation {
+    /// A port event that must happen next.
+    Event(ExpectedPortEvent),
+    /// Port events that must happen next but may happen in any order, for
+    /// when Elm does not specify the order (e.g. the commands in a
+    /// `Cmd.batch`).
+    ///
+    /// The harness sends any subscriptions in the group at the position they
+    /// are listed.
+    Unordered { unordered: Box<[ExpectedPortEvent]> },
+}
+
+// Deserialized by hand so that errors from matchers are not hidden behind
+// "data did not match any variant".
+impl TryFrom<Value> for PortExpectation {
+    type Error = serde_json::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Group {
+            unordered: Box<[ExpectedPortEvent]>,
+        }
+
+        if value.is_object() {
+            serde_json::from_value(value).map(|Group { unordered }| Self::Unordered { unordered })
+        } else {
+            serde_json::from_value(value).map(Self::Event)
+        }
+    }
+}
+
+impl PortExpectation {
+    pub fn events(&self) -> &[ExpectedPortEvent] {
+        match self {
+            PortExpectation::Event(event) => slice::from_ref(event),
+            PortExpectation::Unordered { unordered } => unordered,
+        }
+    }
+}
+
+/// The number of port events a suite's config expects.
+pub fn expected_port_events(expected: &[PortExpectation]) -> usize {
+    expected.iter().map(|e| e.events().len()).sum()
+}
+
+/// The `logs` of a suite's config: either the exact logs or
+/// `{"lines": [...]}` or `{"line-set": [...]}` where each line is a
+/// [`ValuePattern`] (e.g. `{"$regex": "..."}`) matched against that line of
+/// the logs. Lines in a line set may be logged in any order.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged, try_from = "Value")]
+pub enum LogsExpectation {
+    Exact(String),
+    Lines {
+        lines: Box<[ValuePattern]>,
+    },
+    LineSet {
+        #[serde(rename = "line-set")]
+        line_set: Box<[ValuePattern]>,
+    },
+}
+
+impl TryFrom<Value> for LogsExpectation {
+    type Error = serde_json::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields, rename_all = "kebab-case")]
+        enum Patterns {
+            Lines(Box<[ValuePattern]>),
+            LineSet(Box<[ValuePattern]>),
+        }
+
+        if let Value::String(logs) = value {
+            return Ok(Self::Exact(logs));
+        }
+        Ok(match serde_json::from_value(value)? {
+            Patterns::Lines(lines) => Self::Lines { lines },
+            Patterns::LineSet(line_set) => Self::LineSet { line_set },
+        })
+    }
+}
+
+impl Default for LogsExpectation {
+    fn default() -> Self {
+        Self::Exact(String::new())
+    }
+}
+
+/// Pair up expected and actual values that match, pairing as many as
+/// possible. Returns the index of the expected value paired with each actual
+/// value.
+fn pair<E, A>(
+    expected: &[E],
+    actual: &[A],
+    matches: impl Fn(&E, &A) -> bool,
+) -> Vec<Option<usize>> {
+    // Find a chain of re-pairings that frees an expected value for actual
+    // value `a` (Kuhn's algorithm).
+    fn augment(
+        a: usize,
+        edges: &[Vec<usize>],
+        visited: &mut [bool],
+        paired_with: &mut [Option<usize>],
+    ) -> bool {
+        for &e in &edges[a] {
+            if !visited[e] {
+                visited[e] = true;
+                if paired_with[e].is_none_or(|other| augment(other, edges, visited, paired_with)) {
+                    paired_with[e] = Some(a);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    let edges: Vec<Vec<usize>> = actual
+        .iter()
+        .map(|a| {
+            (0..expected.len())
+                .filter(|&e| matches(&expected[e], a))
+                .collect()
+        })
+        .collect();
+    let mut paired_with = vec![None; expected.len()];
+    for a in 0..actual.len() {
+        augment(
+            a,
+            &edges,
+            &mut vec![false; expected.len()],
+            &mut paired_with,
+        );
+    }
+    let mut pairs = vec![None; actual.len()];
+    for (e, a) in paired_with.into_iter().enumerate() {
+        if let Some(a) = a {
+            pairs[a] = Some(e);
+        }
+    }
+    pairs
+}
+
+/// Order `expected` values (which may occur in any order) to line up with
+/// `actual` so that only real differences show in a diff. Unpaired expected
+/// values fill the remaining positions in order.
+fn align_unordered<'e, E, A>(
+    expected: &'e [E],
+    actual: &[A],
+    matches: impl Fn(&E, &A) -> bool,
+) -> Vec<&'e E> {
+    let window = actual.get(..expected.len()).unwrap_or(actual);
+    let pairs = pair(expected, window, matches);
+    let mut leftovers = (0..expected.len()).filter(|e| !pairs.contains(&Some(*e)));
+    (0..expected.len())
+        .map(|i| {
+            let e = pairs
+                .get(i)
+                .copied()
+                .flatten()
+                .or_else(|| leftovers.next())
+                .expect("every expected value fills one position");
+            &expected[e]
+        })
+        .collect()
+}
+
+/// Flatten the expected port events, ordering each unordered group to match
+/// the actual port events as closely as possible.
+fn align<'e>(expected: &'e [PortExpectation], actual: &[PortEvent]) -> Vec<&'e ExpectedPortEvent> {
+    let mut aligned = Vec::with_capacity(expected_port_events(expected));
+    for expectation in expected {
+        match expectation {
+            PortExpectation::Event(event) => aligned.push(event),
+            PortExpectation::Unordered { unordered } => {
+                let rest = actual.get(aligned.len()..).unwrap_or_default();
+                aligned.extend(align_unordered(unordered, rest, port_event_matches));
+            }
+        }
+    }
+    aligned
+}
+
+/// Whether every value in `actual` matches the value at the same position in
+/// `expected`.
+fn all_match<E, A>(expected: &[&E], actual: &[A], matches: impl Fn(&E, &A) -> bool) -> bool {
+    expected.len() == actual.len() && expected.iter().zip(actual).all(|(e, a)| matches(e, a))
+}
+
+/// The port events and logs the harness recorded whilst running an SSCCE.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Recording {
+    pub ports: Box<[PortEvent]>,
+    pub logs: String,
+}
+
+impl Recording {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+}
+
+/// Expected values (or patterns) and the (different) values that actually
+/// occurred.
+#[derive(Debug, Serialize)]
+pub struct Difference<E, A = E> {
+    pub expected: Box<[E]>,
+    pub actual: Box<[A]>,
+}
+
+/// The ways in which a run's port events and logs differed from those in the
+/// suite's config.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExpectationMismatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ports: Option<Difference<ExpectedPortEvent, PortEvent>>,
+    /// The logs split into lines.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logs: Option<Difference<ValuePattern, String>>,
+}
+
+pub fn line_matches(expected: &ValuePattern, actual: &str) -> bool {
+    expected.matches(&Value::String(actual.to_string()))
+}
+
+/// Check that the port events and logs recorded by the harness are the
+/// expected ones.
+pub fn check(
+    expected_ports: &[PortExpectation],
+    expected_logs: &LogsExpectation,
+    recording: &Recording,
+) -> Result<(), ExpectationMismatch> {
+    let Recording { ports, logs } = recording;
+    let aligned = align(expected_ports, ports);
+    let ports = if all_match(&aligned, ports, port_event_matches) {
+        None
+    } else {
+        Some(Difference {
+            expected: aligned.into_iter().cloned().collect(),
+            actual: ports.clone(),
+        })
+    };
+
+    let actual_lines: Box<[String]> = logs.lines().map(str::to_string).collect();
+    let exact_lines: Box<[ValuePattern]>;
+    let aligned: Vec<&ValuePattern> = match expected_logs {
+        LogsExpectation::Exact(logs) => {
+            exact_lines = logs
+                .lines()
+                .map(|line| {
+                    ValuePattern::try_from(Value::String(line.to_string()))
+                        .expect("strings are always valid patterns")
+                })
+                .collect();
+            exact_lines.iter().collect()
+        }
+        LogsExpectation::Lines { lines } => lines.iter().collect(),
+        LogsExpectation::LineSet { line_set } => {
+            align_unordered(line_set, &actual_lines, |e, a| line_matches(e, a))
+        }
+    };
+    let logs = if all_match(&aligned, &actual_lines, |e, a| line_matches(e, a)) {
+        None
+    } else {
+        Some(Difference {
+            expected: aligned.into_iter().cloned().collect(),
+            actual: actual_lines,
+        })
+    };
+
+    if ports.is_none() && logs.is_none() {
+        Ok(())
+    } else {
+        Err(ExpectationMismatch { ports, logs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Expected values are the characters that an actual value may be.
+    fn one_of() -> impl Fn(&&str, &char) -> bool {
+        |e, a| e.contains(*a)
+    }
+
+    #[test]
+    fn pair_finds_a_perfect_matching_that_greedy_pairing_misses() {
+        // Pairing `x` with the first expected value it matches would leave
+        // nothing for `y`.
+        let pairs = pair(&["xy", "x"], &['x', 'y'], one_of());
+        assert_eq!(pairs, vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn pair_leaves_unmatchable_values_unpaired() {
+        let pairs = pair(&["x", "y"], &['x', 'z', 'x'], one_of());
+        assert_eq!(pairs[1], None);
+        assert_eq!(pairs.iter().filter(|e| **e == Some(0)).count(), 1);
+        assert!(!pairs.contains(&Some(1)));
+    }
+
+    #[test]
+    fn align_unordered_lines_up_paired_values() {
+        let expected = ["y", "z", "x"];
+        let aligned = align_unordered(&expected, &['x', 'y', 'q'], one_of());
+        assert_eq!(aligned, vec![&"x", &"y", &"z"]);
+    }
+}
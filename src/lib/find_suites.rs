@@ -1,29 +1,331 @@
+use super::suite;
+use regex::Regex;
+use std::fmt;
 use std::fs;
 use std::io;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Debug)]
 pub enum Error {
     ProvidedPathIsNotDir,
     ReadingDir(io::Error),
+    ReadingSuiteList(PathBuf, io::Error),
+    ListedPathIsNotSuite(PathBuf),
+    NoSuitesSelected,
+    /// Two suites would share an output directory (or one would be inside
+    /// the other's).
+    SuitesShareOutDir(PathBuf, PathBuf),
 }
 
-pub fn find_suites(suites_dir: &Path) -> Result<Box<[PathBuf]>, Error> {
-    if suites_dir.is_dir() {
-        if suites_dir.join("elm.json").exists() {
-            Ok(Box::new([suites_dir.to_path_buf()]))
+/// A boolean expression over the tags of a suite.
+///
+/// Tags are combined with `&` (and), `|` or `,` (or) and `!` (not), and
+/// grouped with parentheses. For example `http & !slow` or `ports,regression`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagExpression {
+    Tag(String),
+    Not(Box<TagExpression>),
+    And(Box<TagExpression>, Box<TagExpression>),
+    Or(Box<TagExpression>, Box<TagExpression>),
+}
+
+impl TagExpression {
+    fn is_met(&self, tags: &[String]) -> bool {
+        match self {
+            Self::Tag(tag) => tags.contains(tag),
+            Self::Not(e) => !e.is_met(tags),
+            Self::And(a, b) => a.is_met(tags) && b.is_met(tags),
+            Self::Or(a, b) => a.is_met(tags) || b.is_met(tags),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidTagExpression(String);
+
+impl FromStr for TagExpression {
+    type Err = InvalidTagExpression;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        struct Parser<'a> {
+            rest: &'a str,
+        }
+
+        impl Parser<'_> {
+            fn peek(&mut self) -> Option<char> {
+                self.rest = self.rest.trim_start();
+                self.rest.chars().next()
+            }
+
+            fn eat(&mut self, c: char) -> bool {
+                if self.peek() == Some(c) {
+                    self.rest = &self.rest[c.len_utf8()..];
+                    true
+                } else {
+                    false
+                }
+            }
+
+            fn or(&mut self) -> Result<TagExpression, String> {
+                let mut lhs = self.and()?;
+                while self.eat('|') || self.eat(',') {
+                    lhs = TagExpression::Or(Box::new(lhs), Box::new(self.and()?));
+                }
+                Ok(lhs)
+            }
+
+            fn and(&mut self) -> Result<TagExpression, String> {
+                let mut lhs = self.unary()?;
+                while self.eat('&') {
+                    lhs = TagExpression::And(Box::new(lhs), Box::new(self.unary()?));
+                }
+                Ok(lhs)
+            }
+
+            fn unary(&mut self) -> Result<TagExpression, String> {
+                if self.eat('!') {
+                    Ok(TagExpression::Not(Box::new(self.unary()?)))
+                } else if self.eat('(') {
+                    let inner = self.or()?;
+                    if self.eat(')') {
+                        Ok(inner)
+                    } else {
+                        Err("expected a closing parenthesis".to_string())
+                    }
+                } else {
+                    self.peek();
+                    let end = self
+                        .rest
+                        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                        .unwrap_or(self.rest.len());
+                    if end == 0 {
+                        Err(format!("expected a tag at \"{}\"", self.rest))
+                    } else {
+                        let (tag, rest) = self.rest.split_at(end);
+                        self.rest = rest;
+                        Ok(TagExpression::Tag(tag.to_string()))
+                    }
+                }
+            }
+        }
+
+        let mut parser = Parser { rest: s };
+        let expression = parser.or().map_err(InvalidTagExpression)?;
+        if parser.peek().is_some() {
+            return Err(InvalidTagExpression(format!(
+                "unexpected \"{}\"",
+                parser.rest
+            )));
+        }
+        Ok(expression)
+    }
+}
+
+impl fmt::Display for InvalidTagExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid tag expression: {}", self.0)
+    }
+}
+
+/// A pattern matched against the path of a suite relative to the suites
+/// directory (using `/` as the separator on every platform), or against its
+/// name if the suites directory is itself the suite.
+///
+/// Patterns starting with `re:` are regular expressions which match if they
+/// match any part of the path. Otherwise the pattern is a glob where `*` and
+/// `?` do not match `/` and `**` matches any number of directories. A glob
+/// also selects every suite within a directory it matches.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    fn is_match(&self, relative_suite_path: &str) -> bool {
+        self.0.is_match(relative_suite_path)
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push_str("(?:/.*)?$");
+    regex
+}
+
+#[derive(Debug)]
+pub struct InvalidPattern(regex::Error);
+
+impl FromStr for Pattern {
+    type Err = InvalidPattern;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let regex = if let Some(regex) = s.strip_prefix("re:") {
+            Regex::new(regex)
         } else {
-            let mut suites = vec![];
-            add_suites(suites_dir, &mut suites)?;
-            suites.sort_unstable();
-            Ok(suites.into_boxed_slice())
+            Regex::new(&glob_to_regex(s))
+        };
+        regex.map(Pattern).map_err(InvalidPattern)
+    }
+}
+
+impl fmt::Display for InvalidPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid suite pattern: {}", self.0)
+    }
+}
+
+/// Which of the suites found within the suites directory should be run.
+#[derive(Debug, Default)]
+pub struct Selection {
+    /// If not empty, only suites matching at least one of these patterns are
+    /// run.
+    pub filters: Vec<Pattern>,
+    /// Suites matching any of these patterns are not run.
+    pub excludes: Vec<Pattern>,
+    /// A file listing the suites to run (one per line, relative to the suites
+    /// directory) instead of searching the suites directory.
+    pub suites_from: Option<PathBuf>,
+    /// Only suites whose tags satisfy this expression are run.
+    pub tags: Option<TagExpression>,
+    /// Suites whose tags satisfy this expression are not run.
+    pub skip_tags: Option<TagExpression>,
+}
+
+impl Selection {
+    fn is_selected(&self, suites_dir: &Path, suite: &Path) -> bool {
+        let relative = relative_name(suites_dir, suite);
+        (self.filters.is_empty() || self.filters.iter().any(|p| p.is_match(&relative)))
+            && !self.excludes.iter().any(|p| p.is_match(&relative))
+            && self.tags_selected(suite)
+    }
+
+    fn tags_selected(&self, suite: &Path) -> bool {
+        if self.tags.is_none() && self.skip_tags.is_none() {
+            return true;
         }
+        // Keep suites with a broken config so that the error gets reported
+        // when elm-torture tries to run them.
+        match suite::get_suite_tags(suite) {
+            Ok(tags) => {
+                self.tags.iter().all(|e| e.is_met(&tags))
+                    && !self.skip_tags.iter().any(|e| e.is_met(&tags))
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+/// The path of `suite` relative to the suites directory (using `/` as the
+/// separator on every platform).
+///
+/// If `suite` is the suites directory its name is used instead.
+pub fn relative_name(suites_dir: &Path, suite: &Path) -> String {
+    let normal_components = |path: &Path| {
+        path.components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut components = normal_components(suite.strip_prefix(suites_dir).unwrap_or(suite));
+    if components.is_empty() {
+        components = suite
+            .canonicalize()
+            .ok()
+            .and_then(|suite| {
+                suite
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .into_iter()
+            .collect();
+    }
+    components.join("/")
+}
+
+/// The directory within the output directory that `suite` is built and run
+/// in.
+pub fn out_dir_name(suites_dir: &Path, suite: &Path) -> PathBuf {
+    relative_name(suites_dir, suite).split('/').collect()
+}
+
+fn check_out_dirs(suites_dir: &Path, suites: &[PathBuf]) -> Result<(), Error> {
+    let mut out_dirs = suites
+        .iter()
+        .map(|suite| (out_dir_name(suites_dir, suite), suite))
+        .collect::<Vec<_>>();
+    out_dirs.sort();
+    // A directory sorts directly before anything within it so only
+    // neighbours need to be checked.
+    for pair in out_dirs.windows(2) {
+        let ((a_dir, a), (b_dir, b)) = (&pair[0], &pair[1]);
+        if b_dir.starts_with(a_dir) {
+            return Err(Error::SuitesShareOutDir((*a).clone(), (*b).clone()));
+        }
+    }
+    Ok(())
+}
+
+pub fn find_suites(suites_dir: &Path, selection: &Selection) -> Result<Box<[PathBuf]>, Error> {
+    if !suites_dir.is_dir() {
+        return Err(Error::ProvidedPathIsNotDir);
+    }
+    let mut suites = if let Some(list) = &selection.suites_from {
+        read_suite_list(suites_dir, list)?
+    } else if suites_dir.join("elm.json").exists() {
+        vec![suites_dir.to_path_buf()]
+    } else {
+        let mut suites = vec![];
+        add_suites(suites_dir, &mut suites)?;
+        suites.sort_unstable();
+        suites
+    };
+    suites.retain(|suite| selection.is_selected(suites_dir, suite));
+    if suites.is_empty() {
+        Err(Error::NoSuitesSelected)
     } else {
-        Err(Error::ProvidedPathIsNotDir)
+        check_out_dirs(suites_dir, &suites)?;
+        Ok(suites.into_boxed_slice())
     }
 }
 
+fn read_suite_list(suites_dir: &Path, list: &Path) -> Result<Vec<PathBuf>, Error> {
+    let contents =
+        fs::read_to_string(list).map_err(|e| Error::ReadingSuiteList(list.to_path_buf(), e))?;
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let suite_path = suites_dir.join(line);
+            if suite_path.join("elm.json").exists() {
+                Ok(suite_path)
+            } else {
+                Err(Error::ListedPathIsNotSuite(suite_path))
+            }
+        })
+        .collect()
+}
+
 fn add_suites(suites_dir: &Path, suites_list: &mut Vec<PathBuf>) -> Result<(), Error> {
     let entries = fs::read_dir(suites_dir).map_err(Error::ReadingDir)?;
     for entry in entries {
@@ -37,3 +339,157 @@ fn add_suites(suites_dir: &Path, suites_list: &mut Vec<PathBuf>) -> Result<(), E
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str) -> TagExpression {
+        TagExpression::Tag(name.to_string())
+    }
+
+    fn parse(s: &str) -> TagExpression {
+        s.parse().unwrap()
+    }
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            parse("a | b & c"),
+            TagExpression::Or(
+                Box::new(tag("a")),
+                Box::new(TagExpression::And(Box::new(tag("b")), Box::new(tag("c"))))
+            )
+        );
+        assert_eq!(parse("a, b & c"), parse("a | b & c"));
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(
+            parse("(a | b) & c"),
+            TagExpression::And(
+                Box::new(TagExpression::Or(Box::new(tag("a")), Box::new(tag("b")))),
+                Box::new(tag("c"))
+            )
+        );
+        assert!(parse("(a | b) & c").is_met(&tags(&["b", "c"])));
+        assert!(!parse("(a | b) & c").is_met(&tags(&["a"])));
+    }
+
+    #[test]
+    fn not_binds_tightest() {
+        assert_eq!(
+            parse("!a & b"),
+            TagExpression::And(
+                Box::new(TagExpression::Not(Box::new(tag("a")))),
+                Box::new(tag("b"))
+            )
+        );
+        assert!(parse("http & !slow").is_met(&tags(&["http"])));
+        assert!(!parse("http & !slow").is_met(&tags(&["http", "slow"])));
+        assert!(parse("!!a").is_met(&tags(&["a"])));
+    }
+
+    #[test]
+    fn invalid_tag_expressions() {
+        for s in &["", "a &", "(a | b", "a b", "a & | b", "!"] {
+            assert!(s.parse::<TagExpression>().is_err(), "{:?} parsed", s);
+        }
+    }
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        pattern.parse::<Pattern>().unwrap().is_match(path)
+    }
+
+    #[test]
+    fn globs() {
+        assert!(matches("http", "http/post"));
+        assert!(matches("http/*", "http/post"));
+        assert!(!matches("h*p/post", "ht/tp/post"));
+        assert!(matches("h?tp", "http"));
+        assert!(!matches("h?tp", "h/tp"));
+        assert!(matches("**/post", "http/post"));
+        assert!(matches("**/post", "post"));
+        assert!(matches("http/**", "http/a/b"));
+        assert!(!matches("http", "https"));
+    }
+
+    #[test]
+    fn globs_escape_regex_syntax() {
+        assert!(matches("a.b", "a.b"));
+        assert!(!matches("a.b", "axb"));
+        assert!(matches("a+(b)", "a+(b)"));
+        assert!(!matches("a+(b)", "aab"));
+    }
+
+    #[test]
+    fn regex_patterns() {
+        assert!(matches("re:po", "http/post"));
+        assert!(matches("re:^http/", "http/post"));
+        assert!(!matches("re:^post", "http/post"));
+        assert!("re:(".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn single_suite_is_matched_by_name() {
+        let suite = Path::new(env!("CARGO_MANIFEST_DIR")).join("suite/hello-world");
+        assert_eq!(relative_name(&suite, &suite), "hello-world");
+        let selection = Selection {
+            filters: vec!["hello-*".parse().unwrap()],
+            ..Selection::default()
+        };
+        assert!(selection.is_selected(&suite, &suite));
+    }
+
+    fn suites_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("suite")
+    }
+
+    fn suite_list(contents: &str) -> tempfile::NamedTempFile {
+        use std::io::Write;
+        let mut list = tempfile::NamedTempFile::new().unwrap();
+        list.write_all(contents.as_bytes()).unwrap();
+        list
+    }
+
+    #[test]
+    fn suite_list_skips_blank_and_comment_lines() {
+        let list =
+            suite_list("\n# The basics\nhello-world\n   \n  # indented comment\n  flags  \n");
+        let suites = read_suite_list(&suites_dir(), list.path()).unwrap();
+        assert_eq!(
+            suites,
+            vec![suites_dir().join("hello-world"), suites_dir().join("flags")]
+        );
+    }
+
+    #[test]
+    fn suite_list_rejects_unknown_suites() {
+        let list = suite_list("hello-world\nno-such-suite\n");
+        match read_suite_list(&suites_dir(), list.path()) {
+            Err(Error::ListedPathIsNotSuite(path)) => {
+                assert_eq!(path, suites_dir().join("no-such-suite"));
+            }
+            other => panic!("expected an unknown suite error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn suite_list_can_name_the_suites_directory() {
+        let suite = suites_dir().join("hello-world");
+        let list = suite_list(".\n");
+        let selection = Selection {
+            suites_from: Some(list.path().to_path_buf()),
+            filters: vec!["hello-world".parse().unwrap()],
+            ..Selection::default()
+        };
+        let suites = find_suites(&suite, &selection).unwrap();
+        assert_eq!(suites.len(), 1);
+        assert_eq!(relative_name(&suite, &suites[0]), "hello-world");
+    }
+}
@@ -1,12 +1,21 @@
 #![allow(clippy::enum_glob_use)]
 
+use super::config::OptimizationLevel;
+use super::expectations::{self, Difference, ExpectationMismatch};
 use super::find_suites;
+use super::network::{NetworkMismatch, OriginMismatches};
+use super::process_tree::Process;
 use super::suite;
 use super::suite::CompileAndRunError;
 use super::suite::GetSuiteConfigError;
+use super::trace::{TimedTraceEvent, Trace};
+use super::websocket::WebSocketMismatch;
+use colored::Colorize;
+use regex::Regex;
 use std::fmt;
 use std::path::Path;
 use std::process;
+use std::sync::LazyLock;
 
 pub fn easy_format<F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result>(func: F) -> impl fmt::Display {
     struct Formatable<F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result> {
@@ -77,11 +86,257 @@ fn compiler_error<'a>(
             SuiteDoesNotExist => {
                 panic!("Path was not suite - this should have been checked already!")
             }
-            DeletingElmStuff(e) => panic!("Could not delete elm-stuff directory! Details: {}", e),
+            CopyingSuite(e) => write!(f, "Could not copy the suite to build it. Details:\n{e}"),
+            PreparingElmHome(e) => write!(
+                f,
+                "Could not prepare an ELM_HOME for the compiler. Details:\n{e}"
+            ),
+            Timeout {
+                after,
+                stdout,
+                stderr,
+            } => write!(
+                f,
+                "Compilation was stopped after {}.{}",
+                humantime::format_duration(*after),
+                easy_format(|f| {
+                    if !stdout.is_empty() || !stderr.is_empty() {
+                        write!(
+                            f,
+                            " Before it stopped the compiler produced the following output:\n{}\n{}",
+                            process_stdout(stdout),
+                            process_stderr(stderr)
+                        )
+                    } else {
+                        write!(f, " (The compiler produced no output)")
+                    }
+                }),
+            ),
+        }
+    })
+}
+
+fn network_mismatch(mismatch: &NetworkMismatch) -> impl fmt::Display + '_ {
+    easy_format(move |f| {
+        use NetworkMismatch::*;
+        match mismatch {
+            Unexpected {
+                index,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Request {} was not expected. Expected one of:",
+                    index + 1
+                )?;
+                for request in &**expected {
+                    write!(f, "\n{}", indented::indented(request))?;
+                }
+                write!(f, "\nbut got:\n{}", indented::indented(actual))
+            }
+            OutOfOrder {
+                index,
+                listed_index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Request {} was made out of order (it is listed as request {}). Expected:\n{}\nbut got:\n{}",
+                index + 1,
+                listed_index + 1,
+                indented::indented(expected),
+                indented::indented(actual)
+            ),
+            Surplus { index, actual } => write!(
+                f,
+                "Request {} was made after every listed request had been made:\n{}",
+                index + 1,
+                indented::indented(actual)
+            ),
+            Missing {
+                listed_index,
+                expected,
+                times,
+                made,
+            } => write!(
+                f,
+                "Listed request {} was made {} time{} but should have been made {}:\n{}",
+                listed_index + 1,
+                made,
+                if *made == 1 { "" } else { "s" },
+                times,
+                indented::indented(expected)
+            ),
+        }
+    })
+}
+
+fn origin_mismatches(origins: &[OriginMismatches]) -> impl fmt::Display + '_ {
+    easy_format(move |f| {
+        for OriginMismatches { origin, mismatches } in origins {
+            if let Some(origin) = origin {
+                write!(
+                    f,
+                    "\n{}",
+                    indented::indented(format_args!("Requests to origin {origin}:"))
+                )?;
+            }
+            for mismatch in &**mismatches {
+                let mismatch = network_mismatch(mismatch);
+                if origin.is_some() {
+                    write!(f, "\n{}", indented::indented(indented::indented(mismatch)))?;
+                } else {
+                    write!(f, "\n{}", indented::indented(mismatch))?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// How many port events happened and the last few events before the run
+/// ended.
+fn trace_summary(trace: &Trace) -> impl fmt::Display + '_ {
+    const EVENTS_SHOWN: usize = 5;
+    easy_format(move |f| {
+        write!(
+            f,
+            "The suite made {} port events ({} were expected).",
+            trace.port_events(),
+            trace.expected_port_events
+        )?;
+        let shown = &trace.events[trace.events.len().saturating_sub(EVENTS_SHOWN)..];
+        if !shown.is_empty() {
+            write!(f, " The last events it traced were:")?;
+            for TimedTraceEvent { time, event } in shown {
+                write!(
+                    f,
+                    "\n{}",
+                    indented::indented(format_args!("{time:.1}ms: {event}"))
+                )?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn expectation_mismatch(mismatch: &ExpectationMismatch) -> impl fmt::Display + '_ {
+    easy_format(move |f| {
+        write!(
+            f,
+            "The suite did not produce the port events and logs in its config."
+        )?;
+        if let Some(ports) = &mismatch.ports {
+            write!(
+                f,
+                "\nPort events (- expected, + actual):{}",
+                indented::indented(aligned_diff(
+                    ports,
+                    expectations::port_event_matches,
+                    |expected| serde_json::to_string(expected)
+                        .expect("port events are always valid json"),
+                    suite::display_port_event
+                ))
+            )?;
         }
+        if let Some(logs) = &mismatch.logs {
+            write!(
+                f,
+                "\nLogs (- expected, + actual):{}",
+                indented::indented(aligned_diff(
+                    logs,
+                    |expected, actual| expectations::line_matches(expected, actual),
+                    |line| match line.raw() {
+                        serde_json::Value::String(line) => line.clone(),
+                        pattern => pattern.to_string(),
+                    },
+                    String::clone
+                ))
+            )?;
+        }
+        Ok(())
+    })
+}
+
+/// Show expected and actual values side by side by index. Values that match
+/// are shown once (as they actually occurred) and values that differ are
+/// shown as removed (expected) followed by added (actual).
+fn aligned_diff<'a, E, A>(
+    Difference { expected, actual }: &'a Difference<E, A>,
+    matches: impl Fn(&E, &A) -> bool + 'a,
+    show_expected: impl Fn(&E) -> String + 'a,
+    show_actual: impl Fn(&A) -> String + 'a,
+) -> impl fmt::Display + 'a {
+    easy_format(move |f| {
+        for index in 0..expected.len().max(actual.len()) {
+            match (expected.get(index), actual.get(index)) {
+                (Some(e), Some(a)) if matches(e, a) => {
+                    write!(f, "\n  {:>3} {}", index + 1, show_actual(a))?;
+                }
+                (e, a) => {
+                    if let Some(e) = e {
+                        let line = format!("- {:>3} {}", index + 1, show_expected(e));
+                        write!(f, "\n{}", line.red())?;
+                    }
+                    if let Some(a) = a {
+                        let line = format!("+ {:>3} {}", index + 1, show_actual(a));
+                        write!(f, "\n{}", line.green())?;
+                    }
+                }
+            }
+        }
+        Ok(())
     })
 }
 
+fn websocket_mismatch(mismatch: &WebSocketMismatch) -> impl fmt::Display + '_ {
+    easy_format(move |f| {
+        use WebSocketMismatch::*;
+        match mismatch {
+            Unexpected {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Step {} of the script expected the message:\n{}\nbut got:\n{}",
+                index + 1,
+                indented::indented(expected),
+                indented::indented(actual)
+            ),
+            Incomplete { index, expected } => write!(
+                f,
+                "The connection ended before step {} of the script ({}).",
+                index + 1,
+                expected
+            ),
+            ExtraConnection => write!(f, "The suite opened more than one connection."),
+            AfterScript { actual } => write!(
+                f,
+                "The suite sent a message after the script had finished:\n{}",
+                indented::indented(actual)
+            ),
+        }
+    })
+}
+
+fn still_running_summary(still_running: &[Process]) -> impl fmt::Display + '_ {
+    easy_format(move |f| {
+        if !still_running.is_empty() {
+            write!(
+                f,
+                "\n\nThe following processes started by the suite are still running:"
+            )?;
+            for process in still_running {
+                write!(f, "\n  {} {}", process.pid, process.command)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[allow(clippy::too_many_lines)]
 fn run_error<'a>(err: &'a suite::RunError, out_dir: &'a Path) -> impl fmt::Display + 'a {
     easy_format(move |f| {
         use suite::RunError::*;
@@ -104,8 +359,11 @@ fn run_error<'a>(err: &'a suite::RunError, out_dir: &'a Path) -> impl fmt::Displ
                 "The expected output exists but cannot be copied. Details:\n{}",
                 err
             ),
-            Runtime(output) => {
+            Runtime { output, trace } => {
                 write!(f, "{}", process_output(&output))?;
+                if let Some(trace) = trace {
+                    write!(f, "\n\n{}", trace_summary(trace))?;
+                }
                 write!(
                     f,
                     "\n\nTo inspect the built files that caused this error see:\n  {}",
@@ -122,9 +380,11 @@ fn run_error<'a>(err: &'a suite::RunError, out_dir: &'a Path) -> impl fmt::Displ
                 after,
                 stdout,
                 stderr,
+                trace,
+                still_running,
             } => write!(
                 f,
-                "Running of the suite was stopped after {}.{}
+                "Running of the suite was stopped after {}.{}{}{}
 
 To inspect the built files that caused this error see: {}",
                 humantime::format_duration(*after),
@@ -142,8 +402,37 @@ To inspect the built files that caused this error see: {}",
                         write!(f, "(The process prouduced no output)")
                     }
                 }),
+                easy_format(|f| match trace {
+                    Some(trace) => write!(f, "\n\n{}", trace_summary(trace)),
+                    None => Ok(()),
+                }),
+                still_running_summary(still_running),
                 out_dir.display()
             ),
+            Blessing(err) => write!(
+                f,
+                "Could not record the output of the suite into its output.json. Details:\n{err}"
+            ),
+            ReadingRecording(err) => write!(
+                f,
+                "Could not read the port events and logs recorded by the harness. Details:\n{err}"
+            ),
+            ExpectationMismatch(mismatch) => write!(f, "{}", expectation_mismatch(mismatch)),
+            Network(origins) => write!(
+                f,
+                "The requests made by the suite did not match its network config.{}",
+                origin_mismatches(origins)
+            ),
+            WebSocket(mismatches) => {
+                write!(
+                    f,
+                    "The messages sent over the WebSocket did not match the script."
+                )?;
+                for mismatch in &**mismatches {
+                    write!(f, "\n{}", indented::indented(websocket_mismatch(mismatch)))?;
+                }
+                Ok(())
+            }
             WritingExpectedOutput(err) => panic!(
                 "Error whilst writing expected output to disk. Details:\n{}",
                 err
@@ -152,6 +441,30 @@ To inspect the built files that caused this error see: {}",
     })
 }
 
+/// The optimisation level and flag set (if any) of a run as used in the
+/// titles of reports, e.g. `dev` or `dev, some-flags`.
+pub fn run_id(opt_level: OptimizationLevel, flag_set: Option<&str>) -> String {
+    match flag_set {
+        Some(flag_set) => format!("{}, {}", opt_level.id(), flag_set),
+        None => opt_level.id().to_string(),
+    }
+}
+
+/// The end of a sentence saying which flag set (if any) a suite was run with.
+pub fn with_flag_set(flag_set: Option<&str>) -> String {
+    flag_set.map_or_else(String::new, |flag_set| {
+        format!(" with flag set {}", flag_set.black().on_white())
+    })
+}
+
+/// `text` without any terminal escape sequences (such as the colours of a
+/// diff or of a compiler's output), for reports read by other programs.
+pub fn without_escapes(text: &str) -> String {
+    static ESCAPE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\x1b(?:\[[0-?]*[ -/]*[@-~]|[@-Z\\-_])").unwrap());
+    ESCAPE.replace_all(text, "").into_owned()
+}
+
 pub fn compile_and_run_error<'a, Pe: AsRef<Path> + 'a, Ps: AsRef<Path> + 'a>(
     err: &'a CompileAndRunError,
     suite: Ps,
@@ -264,16 +577,54 @@ pub fn find_suite_error<'a>(
                 suite_dir.display()
             ),
             ReadingDir(e) => Err(e).unwrap(),
+            ReadingSuiteList(list, e) => writeln!(
+                fmt,
+                "elm-torture could not read the list of suites to run from {}: {}",
+                list.display(),
+                e
+            ),
+            ListedPathIsNotSuite(path) => writeln!(
+                fmt,
+                "{} is listed as a suite to run but it does not contain an elm.json file!",
+                path.display()
+            ),
+            NoSuitesSelected => write!(
+                fmt,
+                "None of the suites in {} were selected to run!
+    Please check the --filter, --exclude, --tags and --skip-tags options and try again.
+",
+                suite_dir.display()
+            ),
+            SuitesShareOutDir(a, b) => writeln!(
+                fmt,
+                "The suites {} and {} would share an output directory!
+    Please rename one of them or run them separately.",
+                a.display(),
+                b.display()
+            ),
         }
     })
 }
 
 pub fn suites_error(err: &suite::SuitesError) -> impl fmt::Display + '_ {
     use suite::SuitesError;
-    easy_format(move |_| match err {
+    easy_format(move |f| {
+        match err {
         SuitesError::ResolvingCompiler(e) => panic!("Could not resolve the elm compiler {:?}", e), // SuitesError::CannotDetectStdlibVariant(e) => {
-                                                                                                   //     panic!("Failed to detect stdlib variant due to error: {:?}", e)
-                                                                                                   // }
+        //     panic!("Failed to detect stdlib variant due to error: {:?}", e)
+        // }
+        SuitesError::PreparingCache(e) => {
+            write!(f, "Could not prepare the compile cache. Details:\n{e}")
+        }
+        SuitesError::PreparingElmHome(e) => write!(
+            f,
+            "Could not copy the elm home (ELM_HOME or ~/.elm) for the compilers to use. Details:\n{e}"
+        ),
+        SuitesError::NothingToRun => write!(
+            f,
+            "There are no compilers or no optimisation levels to run the suites with."
+        ),
+    }
     })
 }
 
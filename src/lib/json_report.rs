@@ -0,0 +1,75 @@
+use super::config::OptimizationLevel;
+use super::suite::{CompileAndRunError, CompileAndRunResults, ElmCompilerPath, Outcome};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Report<'a> {
+    suites: Vec<SuiteReport<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct SuiteReport<'a> {
+    suite: &'a Path,
+    out_dir: &'a Path,
+    runs: Vec<RunReport<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RunReport<'a> {
+    compiler: &'a ElmCompilerPath,
+    opt_level: OptimizationLevel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flag_set: Option<&'a str>,
+    retries: usize,
+    outcome: Outcome,
+    error: Option<&'a CompileAndRunError>,
+}
+
+/// Write the results of an elm-torture run as a json document.
+///
+/// Runs within each suite are sorted by compiler, optimisation level and flag
+/// set so that reports from different runs can be diffed.
+pub fn write_report<Ps: AsRef<Path>>(
+    results: &[CompileAndRunResults<Ps>],
+    w: impl Write,
+) -> serde_json::Result<()> {
+    let suites = results
+        .iter()
+        .map(
+            |CompileAndRunResults {
+                 suite,
+                 sscce_out_dir,
+                 errors,
+             }| {
+                let mut runs: Vec<_> = errors
+                    .iter()
+                    .map(
+                        |((compiler, opt_level, flag_set), (retries, error))| RunReport {
+                            compiler,
+                            opt_level: *opt_level,
+                            flag_set: flag_set.as_deref(),
+                            retries: *retries,
+                            outcome: Outcome::of(error.as_ref()),
+                            error: error.as_ref(),
+                        },
+                    )
+                    .collect();
+                runs.sort_by_key(|run| {
+                    (run.compiler.to_string(), run.opt_level.id(), run.flag_set)
+                });
+                SuiteReport {
+                    suite: suite.as_ref(),
+                    out_dir: sscce_out_dir,
+                    runs,
+                }
+            },
+        )
+        .collect();
+
+    serde_json::to_writer_pretty(w, &Report { suites })
+}
@@ -0,0 +1,149 @@
+use super::formatting;
+use super::suite::{CompileAndRunError, CompileAndRunResults, Outcome};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// A short description of `error` for the `message` attribute of a result.
+fn message(error: &CompileAndRunError) -> &'static str {
+    match error {
+        CompileAndRunError::CompileFailure { allowed: true, .. } => "allowed compile failure",
+        CompileAndRunError::RunFailure { allowed: true, .. } => "allowed run failure",
+        CompileAndRunError::CompileFailure { .. } => "compile failure",
+        CompileAndRunError::RunFailure { .. } => "run failure",
+        CompileAndRunError::ExpectedCompileFailure => {
+            "success when elm-torture expected a compile time failure"
+        }
+        CompileAndRunError::ExpectedRunFailure => {
+            "success when elm-torture expected a run time failure"
+        }
+        _ => "elm-torture could not run the suite",
+    }
+}
+
+/// Escape text for use in XML attribute values and character data.
+///
+/// Terminal escape sequences (for example the colours in compiler output) and
+/// any other characters that XML 1.0 forbids outright are dropped.
+fn escape(s: &str) -> String {
+    let s = formatting::without_escapes(s);
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write the results of an elm-torture run as a junit XML report.
+///
+/// Each suite becomes a `<testsuite>` and each run of it (with a compiler,
+/// optimisation level and flag set) a `<testcase>` within it. Allowed
+/// failures are reported as skipped.
+pub fn write_report<Ps: AsRef<Path>>(
+    results: &[CompileAndRunResults<Ps>],
+    mut w: impl Write,
+) -> io::Result<()> {
+    let mut testsuites = String::new();
+    let (mut total_tests, mut total_failures, mut total_errors, mut total_skipped) = (0, 0, 0, 0);
+
+    for CompileAndRunResults {
+        suite,
+        sscce_out_dir,
+        errors,
+    } in results
+    {
+        let suite_name = escape(&suite.as_ref().display().to_string());
+        let mut runs: Vec<_> = errors.iter().collect();
+        runs.sort_by_key(|((compiler, opt_level, flag_set), _)| {
+            (compiler.to_string(), opt_level.id(), flag_set.clone())
+        });
+
+        let (mut failures, mut errs, mut skipped) = (0, 0, 0);
+        let mut testcases = String::new();
+        for ((compiler, opt_level, flag_set), (retries, error)) in runs {
+            let name = escape(&format!(
+                "{} ({})",
+                compiler,
+                formatting::run_id(*opt_level, flag_set.as_deref())
+            ));
+            let _ = write!(
+                testcases,
+                r#"    <testcase classname="{suite_name}" name="{name}""#
+            );
+            let outcome = Outcome::of(error.as_ref());
+            let element = match (outcome, error) {
+                (Outcome::Success, _) => None,
+                (Outcome::AllowedFailure, _) => {
+                    skipped += 1;
+                    Some("skipped")
+                }
+                (Outcome::UnexpectedSuccess, _)
+                | (
+                    Outcome::Failure,
+                    Some(
+                        CompileAndRunError::CompileFailure { .. }
+                        | CompileAndRunError::RunFailure { .. },
+                    ),
+                ) => {
+                    failures += 1;
+                    Some("failure")
+                }
+                (Outcome::Failure, _) => {
+                    errs += 1;
+                    Some("error")
+                }
+            };
+            let _ = match (element, error) {
+                (Some(element), Some(error)) => writeln!(
+                    testcases,
+                    r#">
+      <{} type="{}" message="{}">{}</{}>
+    </testcase>"#,
+                    element,
+                    outcome.name(),
+                    message(error),
+                    escape(
+                        &formatting::compile_and_run_error(error, suite, sscce_out_dir, *retries)
+                            .to_string()
+                    ),
+                    element
+                ),
+                _ => writeln!(testcases, " />"),
+            };
+        }
+
+        let _ = write!(
+            testsuites,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" skipped="{}">
+{}  </testsuite>
+"#,
+            suite_name,
+            errors.len(),
+            failures,
+            errs,
+            skipped,
+            testcases
+        );
+        total_tests += errors.len();
+        total_failures += failures;
+        total_errors += errs;
+        total_skipped += skipped;
+    }
+
+    write!(
+        w,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="elm-torture" tests="{total_tests}" failures="{total_failures}" errors="{total_errors}" skipped="{total_skipped}">
+{testsuites}</testsuites>
+"#
+    )
+}
@@ -0,0 +1,281 @@
+use core::fmt;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+
+/// Compile a regular expression that must match the whole of a string.
+fn whole_regex(source: &str) -> Result<Box<regex::Regex>, regex::Error> {
+    // Check the regex on its own first so that errors refer to what the
+    // suite's config contains.
+    regex::Regex::new(source)?;
+    regex::Regex::new(&format!("^(?:{source})$")).map(Box::new)
+}
+
+/// The JSON types that `{"$any": type}` can match.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum JsonType {
+    Null,
+    Boolean,
+    Number,
+    String,
+    Array,
+    Object,
+    /// Any JSON value at all.
+    Value,
+}
+
+impl JsonType {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => JsonType::Null,
+            Value::Bool(_) => JsonType::Boolean,
+            Value::Number(_) => JsonType::Number,
+            Value::String(_) => JsonType::String,
+            Value::Array(_) => JsonType::Array,
+            Value::Object(_) => JsonType::Object,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Exact(Value),
+    Array(Box<[Matcher]>),
+    /// An object with exactly these keys (if `partial` is false) or at least
+    /// these keys (if `partial` is true).
+    Object {
+        fields: Box<[(String, Matcher)]>,
+        partial: bool,
+    },
+    Regex(Box<regex::Regex>),
+    Approx {
+        value: f64,
+        tolerance: f64,
+    },
+    Any(JsonType),
+}
+
+/// The tolerance used by `$approx` if `$tolerance` is not given.
+const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug)]
+pub struct InvalidMatcher(String);
+
+impl fmt::Display for InvalidMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Matcher {
+    fn parse(value: &Value) -> Result<Self, InvalidMatcher> {
+        let invalid = |message: &str| Err(InvalidMatcher(format!("{message} in {value}")));
+        match value {
+            Value::Array(values) => values
+                .iter()
+                .map(Matcher::parse)
+                .collect::<Result<_, _>>()
+                .map(Matcher::Array),
+            Value::Object(map) if !map.keys().any(|key| key.starts_with('$')) => {
+                Matcher::parse_fields(map, false)
+            }
+            Value::Object(map) => {
+                let mut keys: Vec<&str> = map.keys().map(String::as_str).collect();
+                keys.sort_unstable();
+                match (keys.as_slice(), map.values().next()) {
+                    (["$literal"], Some(literal)) => Ok(Matcher::Exact(literal.clone())),
+                    (["$regex"], Some(Value::String(regex))) => whole_regex(regex)
+                        .map(Matcher::Regex)
+                        .map_err(|e| InvalidMatcher(e.to_string())),
+                    (["$any"], Some(json_type)) => serde_json::from_value(json_type.clone())
+                        .map(Matcher::Any)
+                        .map_err(|e| InvalidMatcher(format!("{e} in {value}"))),
+                    (["$partial"], Some(Value::Object(fields))) => {
+                        Matcher::parse_fields(fields, true)
+                    }
+                    (["$approx"] | ["$approx", "$tolerance"], _) => {
+                        match (
+                            map["$approx"].as_f64(),
+                            map.get("$tolerance")
+                                .map_or(Some(DEFAULT_TOLERANCE), Value::as_f64),
+                        ) {
+                            (Some(value), Some(tolerance)) => {
+                                Ok(Matcher::Approx { value, tolerance })
+                            }
+                            _ => invalid("`$approx` and `$tolerance` must be numbers"),
+                        }
+                    }
+                    _ => invalid("Unknown matcher"),
+                }
+            }
+            value => Ok(Matcher::Exact(value.clone())),
+        }
+    }
+
+    fn parse_fields(fields: &Map<String, Value>, partial: bool) -> Result<Self, InvalidMatcher> {
+        Ok(Matcher::Object {
+            fields: fields
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), Matcher::parse(value)?)))
+                .collect::<Result<_, _>>()?,
+            partial,
+        })
+    }
+
+    fn matches(&self, actual: &Value) -> bool {
+        match (self, actual) {
+            (Matcher::Exact(expected), actual) => expected == actual,
+            (Matcher::Array(matchers), Value::Array(values)) => {
+                matchers.len() == values.len()
+                    && matchers.iter().zip(values).all(|(m, v)| m.matches(v))
+            }
+            (Matcher::Object { fields, partial }, Value::Object(map)) => {
+                (*partial || fields.len() == map.len())
+                    && fields
+                        .iter()
+                        .all(|(key, m)| map.get(key).is_some_and(|v| m.matches(v)))
+            }
+            (Matcher::Regex(regex), Value::String(s)) => regex.is_match(s),
+            (Matcher::Approx { value, tolerance }, Value::Number(n)) => {
+                n.as_f64().is_some_and(|n| (n - value).abs() <= *tolerance)
+            }
+            (Matcher::Any(json_type), actual) => {
+                *json_type == JsonType::Value || *json_type == JsonType::of(actual)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A JSON value from a suite's config that a port argument is matched
+/// against.
+///
+/// Values match themselves except for objects whose keys start with `$`,
+/// which are matchers:
+///
+/// * `{"$regex": "..."}` matches strings that the regular expression matches
+///   (the whole string must match).
+/// * `{"$approx": x, "$tolerance": t}` matches numbers within `t` of `x`
+///   (`$tolerance` is optional).
+/// * `{"$any": "number"}` matches any value of the given JSON type (or any
+///   value at all for `"value"`).
+/// * `{"$partial": {...}}` matches objects with at least the given keys.
+/// * `{"$literal": ...}` matches the given value exactly, even if it
+///   contains matchers.
+///
+/// Matchers may be nested within arrays and objects.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "Value", into = "Value")]
+pub struct ValuePattern {
+    raw: Value,
+    matcher: Matcher,
+}
+
+impl ValuePattern {
+    pub fn matches(&self, actual: &Value) -> bool {
+        self.matcher.matches(actual)
+    }
+
+    /// The pattern as it is written in the suite's config.
+    pub fn raw(&self) -> &Value {
+        &self.raw
+    }
+}
+
+impl TryFrom<Value> for ValuePattern {
+    type Error = InvalidMatcher;
+
+    fn try_from(raw: Value) -> Result<Self, Self::Error> {
+        let matcher = Matcher::parse(&raw)?;
+        Ok(Self { raw, matcher })
+    }
+}
+
+impl From<ValuePattern> for Value {
+    fn from(pattern: ValuePattern) -> Self {
+        pattern.raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn matcher(pattern: &Value) -> Matcher {
+        Matcher::parse(pattern).unwrap()
+    }
+
+    #[test]
+    fn plain_values_match_themselves() {
+        let m = matcher(&json!({"a": [1, "two", null]}));
+        assert!(m.matches(&json!({"a": [1, "two", null]})));
+        assert!(!m.matches(&json!({"a": [1, "two"]})));
+        assert!(!m.matches(&json!({"a": [1, "two", null], "b": true})));
+    }
+
+    #[test]
+    fn regex_matches_whole_strings() {
+        let m = matcher(&json!({"$regex": "a+b"}));
+        assert!(m.matches(&json!("aab")));
+        assert!(!m.matches(&json!("aabc")));
+        assert!(!m.matches(&json!(1)));
+        assert!(Matcher::parse(&json!({"$regex": "("})).is_err());
+    }
+
+    #[test]
+    fn approx() {
+        let m = matcher(&json!({"$approx": 0.3}));
+        assert!(m.matches(&json!(0.300_000_000_000_000_04)));
+        assert!(!m.matches(&json!(0.31)));
+        assert!(!m.matches(&json!("0.3")));
+        let m = matcher(&json!({"$approx": 1, "$tolerance": 0.5}));
+        assert!(m.matches(&json!(1.4)));
+        assert!(!m.matches(&json!(1.6)));
+    }
+
+    #[test]
+    fn approx_needs_numbers() {
+        assert!(Matcher::parse(&json!({"$approx": 1, "$tolerance": "small"})).is_err());
+        assert!(Matcher::parse(&json!({"$approx": "1"})).is_err());
+    }
+
+    #[test]
+    fn any() {
+        assert!(matcher(&json!({"$any": "number"})).matches(&json!(2)));
+        assert!(!matcher(&json!({"$any": "number"})).matches(&json!("2")));
+        assert!(matcher(&json!({"$any": "value"})).matches(&json!(null)));
+        assert!(Matcher::parse(&json!({"$any": "integer"})).is_err());
+    }
+
+    #[test]
+    fn partial_objects() {
+        let m = matcher(&json!({"$partial": {"a": {"$any": "number"}}}));
+        assert!(m.matches(&json!({"a": 1, "b": 2})));
+        assert!(!m.matches(&json!({"b": 2})));
+        assert!(!m.matches(&json!([1])));
+    }
+
+    #[test]
+    fn literal() {
+        let m = matcher(&json!({"$literal": {"$any": "number"}}));
+        assert!(m.matches(&json!({"$any": "number"})));
+        assert!(!m.matches(&json!(1)));
+    }
+
+    #[test]
+    fn nested_matchers() {
+        let m = matcher(&json!([{"$regex": "x.*"}, {"n": {"$approx": 2}}]));
+        assert!(m.matches(&json!(["xyz", {"n": 2.000_000_000_1}])));
+        assert!(!m.matches(&json!(["yz", {"n": 2}])));
+    }
+
+    #[test]
+    fn unknown_matchers_are_rejected() {
+        assert!(Matcher::parse(&json!({"$unknown": 1})).is_err());
+        assert!(Matcher::parse(&json!({"$regex": "a", "extra": 1})).is_err());
+        assert!(Matcher::parse(&json!([{"$approx": 1, "$tol": 1}])).is_err());
+    }
+}
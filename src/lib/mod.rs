@@ -1,6 +1,18 @@
+pub mod cache;
 pub mod cli;
 pub mod config;
+pub mod elm_home;
+pub mod expectations;
 pub mod find_suites;
 pub mod formatting;
+pub mod json_report;
+pub mod junit;
+pub mod matcher;
+pub mod network;
+pub mod process_tree;
+pub mod reporter;
+pub mod scratch;
 pub mod server_pool;
 pub mod suite;
+pub mod trace;
+pub mod websocket;
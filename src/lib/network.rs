@@ -0,0 +1,817 @@
+use super::server_pool::Protocol;
+use super::server_pool::ServerId;
+use super::server_pool::ServerPool;
+use core::fmt;
+use futures::stream::{self, StreamExt};
+use log::debug;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+use warp::hyper::Body;
+use warp::Filter;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl Method {
+    fn to_http(self) -> warp::http::Method {
+        match self {
+            Method::Get => warp::http::Method::GET,
+            Method::Post => warp::http::Method::POST,
+            Method::Put => warp::http::Method::PUT,
+            Method::Patch => warp::http::Method::PATCH,
+            Method::Delete => warp::http::Method::DELETE,
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_http())
+    }
+}
+
+/// A request the SSCCE is expected to make.
+///
+/// Only the headers listed are checked (other headers the request has are
+/// ignored) and the body is only checked if one is given.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Request {
+    method: Method,
+    url: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+impl Request {
+    fn matches(&self, actual: &ActualRequest) -> bool {
+        self.method.to_http().as_str() == actual.method
+            && self.url == actual.url
+            && self
+                .headers
+                .iter()
+                .all(|(name, value)| actual.headers.get(&name.to_ascii_lowercase()) == Some(value))
+            && self.body.iter().all(|b| *b == actual.body)
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.url)?;
+        for (name, value) in &self.headers {
+            write!(f, "\n{name}: {value}")?;
+        }
+        if let Some(body) = &self.body {
+            write!(f, "\n\n{body}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A request the mock server actually received.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ActualRequest {
+    method: String,
+    url: String,
+    headers: BTreeMap<String, String>,
+    body: String,
+}
+
+impl ActualRequest {
+    fn new(
+        method: &warp::http::Method,
+        path: &warp::path::FullPath,
+        headers: &warp::http::HeaderMap,
+        body: &[u8],
+    ) -> Self {
+        Self {
+            method: method.to_string(),
+            url: path.as_str().to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.as_str().to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+            body: String::from_utf8_lossy(body).into_owned(),
+        }
+    }
+}
+
+impl fmt::Display for ActualRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.url)?;
+        for (name, value) in &self.headers {
+            write!(f, "\n{name}: {value}")?;
+        }
+        if !self.body.is_empty() {
+            write!(f, "\n\n{}", self.body)?;
+        }
+        Ok(())
+    }
+}
+
+/// Part of a response body that is sent on its own.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Chunk {
+    /// How long to wait before sending this chunk.
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pause: Option<Duration>,
+    body: String,
+}
+
+/// The response the mock server gives to a request.
+///
+/// A plain string is shorthand for a `200 OK` response with that body. A
+/// response with `chunks` rather than a `body` sends each chunk separately
+/// (with a `Content-Length` header giving the length of the whole body).
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum Response {
+    Body(String),
+    #[serde(rename_all = "kebab-case")]
+    Chunked {
+        #[serde(default = "Response::default_status")]
+        status: u16,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+        chunks: Box<[Chunk]>,
+    },
+    #[serde(rename_all = "kebab-case")]
+    Full {
+        #[serde(default = "Response::default_status")]
+        status: u16,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+        #[serde(default)]
+        body: String,
+    },
+}
+
+impl Response {
+    fn default_status() -> u16 {
+        200
+    }
+
+    /// Deserialize a response, rejecting any that cannot be sent (because of
+    /// an invalid status code or header) when the suite config is read.
+    fn deserialize_valid<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let response = Self::deserialize(deserializer)?;
+        response
+            .to_http()
+            .map_err(|e| serde::de::Error::custom(format!("invalid response: {e}")))?;
+        Ok(response)
+    }
+
+    fn to_http(&self) -> warp::http::Result<warp::http::Response<Body>> {
+        let with_headers = |status: u16, headers: &BTreeMap<String, String>| {
+            headers.iter().fold(
+                warp::http::Response::builder().status(status),
+                |builder, (name, value)| builder.header(name.as_str(), value.as_str()),
+            )
+        };
+        match self {
+            Response::Body(body) => warp::http::Response::builder().body(Body::from(body.clone())),
+            Response::Chunked {
+                status,
+                headers,
+                chunks,
+            } => {
+                let length: usize = chunks.iter().map(|chunk| chunk.body.len()).sum();
+                let chunks = chunks.to_vec();
+                let body = stream::iter(chunks).then(|Chunk { pause, body }| async move {
+                    if let Some(pause) = pause {
+                        tokio::time::sleep(pause).await;
+                    }
+                    Ok::<_, Infallible>(body)
+                });
+                let mut builder = with_headers(*status, headers);
+                if !headers
+                    .keys()
+                    .any(|name| name.eq_ignore_ascii_case("content-length"))
+                {
+                    builder = builder.header("content-length", length);
+                }
+                builder.body(Body::wrap_stream(body))
+            }
+            Response::Full {
+                status,
+                headers,
+                body,
+            } => with_headers(*status, headers).body(Body::from(body.clone())),
+        }
+    }
+}
+
+/// How many times a request may be made.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Times {
+    Exactly(usize),
+    Keyword(TimesKeyword),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimesKeyword {
+    AtLeastOnce,
+    Any,
+}
+
+impl Times {
+    fn min(self) -> usize {
+        match self {
+            Times::Exactly(n) => n,
+            Times::Keyword(TimesKeyword::AtLeastOnce) => 1,
+            Times::Keyword(TimesKeyword::Any) => 0,
+        }
+    }
+
+    fn max(self) -> usize {
+        match self {
+            Times::Exactly(n) => n,
+            Times::Keyword(_) => usize::MAX,
+        }
+    }
+}
+
+impl Default for Times {
+    fn default() -> Self {
+        Times::Exactly(1)
+    }
+}
+
+impl fmt::Display for Times {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Times::Exactly(1) => write!(f, "exactly once"),
+            Times::Exactly(n) => write!(f, "exactly {n} times"),
+            Times::Keyword(TimesKeyword::AtLeastOnce) => write!(f, "at least once"),
+            Times::Keyword(TimesKeyword::Any) => write!(f, "any number of times"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct NetworkItem {
+    request: Request,
+    #[serde(deserialize_with = "Response::deserialize_valid")]
+    response: Response,
+    #[serde(default, skip_serializing_if = "is_once")]
+    times: Times,
+    /// Hold the response until this many port events have occurred.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after_port_event: Option<usize>,
+    /// Wait this long before responding (after any port event the response
+    /// is held for).
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    delay: Option<Duration>,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_once(times: &Times) -> bool {
+    *times == Times::default()
+}
+
+/// The order in which the requests listed in a suite's network config must
+/// be made.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Order {
+    /// In the order they are listed.
+    #[default]
+    Sequential,
+    /// In any order.
+    Any,
+}
+
+/// The requests made to a mock origin.
+///
+/// A plain list of requests is shorthand for requests made in sequence.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum Network {
+    Sequential(Box<[NetworkItem]>),
+    #[serde(rename_all = "kebab-case")]
+    Config {
+        #[serde(default)]
+        order: Order,
+        requests: Box<[NetworkItem]>,
+    },
+}
+
+impl Network {
+    fn order(&self) -> Order {
+        match self {
+            Network::Sequential(_) => Order::Sequential,
+            Network::Config { order, .. } => *order,
+        }
+    }
+
+    fn items(&self) -> &[NetworkItem] {
+        match self {
+            Network::Sequential(items)
+            | Network::Config {
+                requests: items, ..
+            } => items,
+        }
+    }
+
+    fn holds_responses(&self) -> bool {
+        self.items()
+            .iter()
+            .any(|item| item.after_port_event.is_some())
+    }
+}
+
+/// The `network` key of a suite's config.
+///
+/// Either the requests made to a single origin or the requests made to each
+/// of several named origins (each of which gets its own mock server).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum NetworkConfig {
+    Origins {
+        origins: BTreeMap<String, Arc<Network>>,
+    },
+    Single(Arc<Network>),
+}
+
+impl NetworkConfig {
+    /// The origins to start mock servers for. The origin of a suite with a
+    /// single origin is unnamed.
+    pub fn origins(&self) -> Vec<(Option<&str>, &Arc<Network>)> {
+        match self {
+            NetworkConfig::Origins { origins } => origins
+                .iter()
+                .map(|(name, network)| (Some(name.as_str()), network))
+                .collect(),
+            NetworkConfig::Single(network) => vec![(None, network)],
+        }
+    }
+
+    /// Whether any response is held until a port event, in which case the
+    /// harness must tell the mock servers about port events.
+    pub fn holds_responses(&self) -> bool {
+        self.origins()
+            .iter()
+            .any(|(_, network)| network.holds_responses())
+    }
+}
+
+/// A way in which the requests made by an SSCCE differed from those listed
+/// in its `network` config.
+///
+/// Requests made are indexed by the order in which they were made and
+/// requests in the config are indexed by the order in which they are listed.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum NetworkMismatch {
+    /// The request made at `index` did not match any of the requests that
+    /// could have been made at that point.
+    Unexpected {
+        index: usize,
+        expected: Box<[Request]>,
+        actual: ActualRequest,
+    },
+    /// The request made at `index` was listed (at `listed_index`) but only
+    /// after `expected` which had not been made enough times.
+    #[serde(rename_all = "kebab-case")]
+    OutOfOrder {
+        index: usize,
+        listed_index: usize,
+        expected: Request,
+        actual: ActualRequest,
+    },
+    /// The request made at `index` was made after every listed request had
+    /// been made as many times as allowed.
+    Surplus { index: usize, actual: ActualRequest },
+    /// The request listed at `listed_index` was not made enough times.
+    #[serde(rename_all = "kebab-case")]
+    Missing {
+        listed_index: usize,
+        expected: Request,
+        times: Times,
+        made: usize,
+    },
+}
+
+/// The ways in which the requests made to one origin differed from its
+/// config.
+#[derive(Debug, Serialize, Clone)]
+pub struct OriginMismatches {
+    /// `None` for the origin of a suite with a single origin.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    pub mismatches: Box<[NetworkMismatch]>,
+}
+
+#[derive(Debug)]
+struct NetworkState {
+    requests_made: usize,
+    /// When requests must be made in sequence, the earliest listed request
+    /// that may still be made.
+    cursor: usize,
+    counts: Vec<usize>,
+    mismatches: Vec<NetworkMismatch>,
+    /// The number of port events the harness has reported.
+    port_events: usize,
+}
+
+impl NetworkState {
+    fn new(network: &Network) -> Self {
+        Self {
+            requests_made: 0,
+            cursor: 0,
+            counts: vec![0; network.items().len()],
+            mismatches: Vec::new(),
+            port_events: 0,
+        }
+    }
+
+    fn can_make(&self, items: &[NetworkItem], i: usize) -> bool {
+        self.counts[i] < items[i].times.max()
+    }
+
+    /// Find the listed request that an actual request should be matched to.
+    fn find_match(
+        &self,
+        network: &Network,
+        actual: &ActualRequest,
+    ) -> Result<usize, Box<NetworkMismatch>> {
+        let items = network.items();
+        let index = self.requests_made;
+        let matches = |i: usize| self.can_make(items, i) && items[i].request.matches(actual);
+        // No listed request that could be made matches.
+        let mismatch = |candidates: Vec<usize>| {
+            Box::new(if candidates.is_empty() {
+                NetworkMismatch::Surplus {
+                    index,
+                    actual: actual.clone(),
+                }
+            } else {
+                NetworkMismatch::Unexpected {
+                    index,
+                    expected: candidates
+                        .into_iter()
+                        .map(|i| items[i].request.clone())
+                        .collect(),
+                    actual: actual.clone(),
+                }
+            })
+        };
+        match network.order() {
+            Order::Any => (0..items.len()).find(|&i| matches(i)).ok_or_else(|| {
+                mismatch(
+                    (0..items.len())
+                        .filter(|&i| self.can_make(items, i))
+                        .collect(),
+                )
+            }),
+            Order::Sequential => {
+                let mut candidates = Vec::new();
+                for i in self.cursor..items.len() {
+                    if matches(i) {
+                        return Ok(i);
+                    }
+                    if self.can_make(items, i) {
+                        candidates.push(i);
+                    }
+                    if self.counts[i] < items[i].times.min() {
+                        return Err(match (i + 1..items.len()).find(|&later| matches(later)) {
+                            Some(listed_index) => Box::new(NetworkMismatch::OutOfOrder {
+                                index,
+                                listed_index,
+                                expected: items[i].request.clone(),
+                                actual: actual.clone(),
+                            }),
+                            None => mismatch(candidates),
+                        });
+                    }
+                }
+                Err(mismatch(candidates))
+            }
+        }
+    }
+}
+
+pub(crate) struct Server<'pool> {
+    id: ServerId<'pool>,
+    origin: Option<String>,
+    protocol: Protocol,
+    network: Arc<Network>,
+    state: Arc<Mutex<NetworkState>>,
+}
+
+/// When (and what) the mock server should respond to a request.
+struct PendingResponse {
+    response: warp::http::Response<Body>,
+    after_port_event: Option<usize>,
+    delay: Option<Duration>,
+}
+
+impl PendingResponse {
+    async fn deliver(self, mut port_events: watch::Receiver<usize>) -> warp::http::Response<Body> {
+        if let Some(after_port_event) = self.after_port_event {
+            while *port_events.borrow() < after_port_event {
+                if port_events.changed().await.is_err() {
+                    break;
+                }
+            }
+        }
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        self.response
+    }
+}
+
+impl<'pool> Server<'pool> {
+    pub fn new(
+        server_pool: &'pool ServerPool,
+        protocol: Protocol,
+        a: SocketAddr,
+        origin: Option<&str>,
+        network: Arc<Network>,
+    ) -> Self {
+        let state = Arc::new(Mutex::new(NetworkState::new(&network)));
+        let (port_events_tx, port_events_rx) = watch::channel(0);
+        let port_events_tx = Arc::new(port_events_tx);
+        let handler_state = Arc::clone(&state);
+        let handler_network = Arc::clone(&network);
+        let port_events_state = Arc::clone(&state);
+        // The harness posts the number of port events that have occurred
+        // here so that held responses can be released.
+        let port_events = warp::post()
+            .and(warp::path!("__elm-torture" / "port-events" / usize))
+            .map(move |count: usize| {
+                debug!("Harness reports {} port events", count);
+                let mut state = port_events_state.lock().unwrap();
+                // Reports may arrive out of order.
+                state.port_events = state.port_events.max(count);
+                let _ = port_events_tx.send(state.port_events);
+                warp::reply()
+            });
+        let requests = warp::path::full()
+            .and(warp::method())
+            .and(warp::header::headers_cloned())
+            .and(warp::body::bytes())
+            .and_then(
+                move |name: warp::path::FullPath,
+                      method: warp::http::Method,
+                      headers: warp::http::HeaderMap,
+                      body: warp::hyper::body::Bytes| {
+                    debug!("Request with url {} and method {}", name.as_str(), method);
+                    let actual = ActualRequest::new(&method, &name, &headers, &body);
+                    let pending = {
+                        let mut state = handler_state.lock().unwrap();
+                        respond(&mut state, &handler_network, &actual)
+                    };
+                    let port_events = port_events_rx.clone();
+                    async move { Ok::<_, Infallible>(pending.deliver(port_events).await) }
+                },
+            );
+        Self {
+            id: server_pool.start(port_events.or(requests).with(warp::log("api")), protocol, a),
+            origin: origin.map(str::to_string),
+            protocol,
+            network,
+            state,
+        }
+    }
+
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    pub fn url(&self) -> SocketAddr {
+        self.id.url
+    }
+
+    pub fn protocol(&self) -> String {
+        match self.protocol {
+            Protocol::Http => "http://".into(),
+            Protocol::Https => "https://".into(),
+        }
+    }
+
+    /// Check that the SSCCE made exactly the expected requests.
+    ///
+    /// Requests that were not made enough times are only reported if
+    /// `finished` is true (i.e. the SSCCE ran to completion).
+    pub fn check(&self, finished: bool) -> Result<(), OriginMismatches> {
+        let state = self.state.lock().unwrap();
+        let mut mismatches = state.mismatches.clone();
+        if finished && mismatches.is_empty() {
+            mismatches.extend(
+                self.network
+                    .items()
+                    .iter()
+                    .zip(state.counts.iter())
+                    .enumerate()
+                    .filter(|(_, (item, made))| **made < item.times.min())
+                    .map(|(listed_index, (item, made))| NetworkMismatch::Missing {
+                        listed_index,
+                        expected: item.request.clone(),
+                        times: item.times,
+                        made: *made,
+                    }),
+            );
+        }
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(OriginMismatches {
+                origin: self.origin.clone(),
+                mismatches: mismatches.into_boxed_slice(),
+            })
+        }
+    }
+}
+
+fn respond(state: &mut NetworkState, network: &Network, actual: &ActualRequest) -> PendingResponse {
+    let found = state.find_match(network, actual);
+    state.requests_made += 1;
+    match found {
+        Ok(i) => {
+            state.counts[i] += 1;
+            if network.order() == Order::Sequential {
+                state.cursor = i;
+            }
+            let item = &network.items()[i];
+            PendingResponse {
+                // Responses are checked when the suite config is read.
+                response: item.response.to_http().unwrap_or_else(|e| {
+                    internal_server_error(format!("elm-torture: invalid response: {e}"))
+                }),
+                after_port_event: item.after_port_event,
+                delay: item.delay,
+            }
+        }
+        Err(mismatch) => {
+            state.mismatches.push(*mismatch);
+            PendingResponse {
+                response: internal_server_error("elm-torture: unexpected request"),
+                after_port_event: None,
+                delay: None,
+            }
+        }
+    }
+}
+
+fn internal_server_error(body: impl Into<Body>) -> warp::http::Response<Body> {
+    let mut response = warp::http::Response::new(body.into());
+    *response.status_mut() = warp::http::StatusCode::INTERNAL_SERVER_ERROR;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn network(config: serde_json::Value) -> Network {
+        serde_json::from_value(config).unwrap()
+    }
+
+    fn get(url: &str) -> serde_json::Value {
+        json!({"method": "get", "url": url})
+    }
+
+    /// Make a `GET` request for `url`, returning the mismatch (if any) it
+    /// caused.
+    fn make(state: &mut NetworkState, network: &Network, url: &str) -> Option<NetworkMismatch> {
+        let actual = ActualRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: BTreeMap::new(),
+            body: String::new(),
+        };
+        let before = state.mismatches.len();
+        respond(state, network, &actual);
+        state.mismatches.get(before).cloned()
+    }
+
+    #[test]
+    fn sequential_requests_made_out_of_order() {
+        let network = network(json!([
+            {"request": get("/a"), "response": "a"},
+            {"request": get("/b"), "response": "b"},
+        ]));
+        let mut state = NetworkState::new(&network);
+        assert!(matches!(
+            make(&mut state, &network, "/b"),
+            Some(NetworkMismatch::OutOfOrder {
+                index: 0,
+                listed_index: 1,
+                ..
+            })
+        ));
+        assert!(make(&mut state, &network, "/a").is_none());
+        assert!(make(&mut state, &network, "/b").is_none());
+    }
+
+    #[test]
+    fn requests_made_too_many_times() {
+        for order in &["sequential", "any"] {
+            let network = network(json!({
+                "order": order,
+                "requests": [
+                    {"request": get("/a"), "response": "a", "times": 2},
+                    {"request": get("/b"), "response": "b", "times": "at-least-once"},
+                ],
+            }));
+            let mut state = NetworkState::new(&network);
+            assert!(make(&mut state, &network, "/a").is_none());
+            assert!(make(&mut state, &network, "/a").is_none());
+            assert!(matches!(
+                make(&mut state, &network, "/a"),
+                Some(NetworkMismatch::Unexpected { index: 2, .. })
+            ));
+            assert!(make(&mut state, &network, "/b").is_none());
+            assert!(make(&mut state, &network, "/b").is_none());
+            assert_eq!(state.counts, vec![2, 2]);
+        }
+    }
+
+    #[test]
+    fn requests_made_after_every_request_is_used_up() {
+        let network = network(json!([{"request": get("/a"), "response": "a"}]));
+        let mut state = NetworkState::new(&network);
+        assert!(make(&mut state, &network, "/a").is_none());
+        assert!(matches!(
+            make(&mut state, &network, "/a"),
+            Some(NetworkMismatch::Surplus { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn any_times_between_sequential_requests() {
+        let network = network(json!([
+            {"request": get("/a"), "response": "a"},
+            {"request": get("/poll"), "response": "p", "times": "any"},
+            {"request": get("/b"), "response": "b"},
+        ]));
+
+        // The optional request may be made any number of times (including
+        // none) between its neighbours.
+        for polls in 0..3 {
+            let mut state = NetworkState::new(&network);
+            assert!(make(&mut state, &network, "/a").is_none());
+            for _ in 0..polls {
+                assert!(make(&mut state, &network, "/poll").is_none());
+            }
+            assert!(make(&mut state, &network, "/b").is_none());
+            assert_eq!(state.counts, vec![1, polls, 1]);
+        }
+
+        // But not before the request listed ahead of it.
+        let mut state = NetworkState::new(&network);
+        assert!(matches!(
+            make(&mut state, &network, "/poll"),
+            Some(NetworkMismatch::OutOfOrder {
+                listed_index: 1,
+                ..
+            })
+        ));
+
+        // Nor after the request listed behind it.
+        let mut state = NetworkState::new(&network);
+        assert!(make(&mut state, &network, "/a").is_none());
+        assert!(make(&mut state, &network, "/b").is_none());
+        assert!(make(&mut state, &network, "/poll").is_some());
+    }
+}
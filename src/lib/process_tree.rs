@@ -0,0 +1,126 @@
+use serde::Serialize;
+use std::io;
+use std::process::{Child, Command};
+use std::time::Duration;
+
+/// A process started (directly or indirectly) by another process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Process {
+    pub pid: u32,
+    pub command: String,
+}
+
+/// Make the process that `command` spawns the leader of a new process group
+/// so that it can be stopped along with every process it starts.
+pub fn new_process_group(command: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    command
+}
+
+/// Stop `child` and the rest of its process group, returning any processes
+/// started by `child` that are still running afterwards.
+///
+/// The group is sent `SIGTERM` and then, after `grace` (or as soon as `child`
+/// exits), `SIGKILL`. Processes that have left the group cannot be stopped
+/// this way and so are reported instead.
+///
+/// `child` must have been spawned by a command passed to `new_process_group`.
+#[cfg(unix)]
+pub fn kill(child: &mut Child, grace: Duration) -> io::Result<Box<[Process]>> {
+    use std::convert::TryFrom;
+    use wait_timeout::ChildExt;
+
+    fn signal_group(leader: u32, signal: libc::c_int) -> io::Result<()> {
+        let leader = libc::pid_t::try_from(leader)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        // Safety: `kill` has no memory safety requirements.
+        if unsafe { libc::kill(-leader, signal) } == 0 {
+            return Ok(());
+        }
+        match io::Error::last_os_error() {
+            // Everything in the group has already exited.
+            e if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            e => Err(e),
+        }
+    }
+
+    let descendants = descendants(child.id());
+    signal_group(child.id(), libc::SIGTERM)?;
+    child.wait_timeout(grace)?;
+    signal_group(child.id(), libc::SIGKILL)?;
+    child.wait()?;
+
+    let running = running_processes().unwrap_or_default();
+    Ok(descendants
+        .into_iter()
+        .filter(|descendant| running.iter().any(|running| running.process == *descendant))
+        .collect())
+}
+
+#[cfg(not(unix))]
+pub fn kill(child: &mut Child, _grace: Duration) -> io::Result<Box<[Process]>> {
+    child.kill()?;
+    child.wait()?;
+    Ok(Box::new([]))
+}
+
+#[cfg(unix)]
+struct RunningProcess {
+    process: Process,
+    parent: u32,
+}
+
+/// Every process (other than zombies) that is running, or `None` if they
+/// cannot be listed.
+#[cfg(unix)]
+fn running_processes() -> Option<Vec<RunningProcess>> {
+    let output = Command::new("ps")
+        .args([
+            "-A", "-o", "pid=", "-o", "ppid=", "-o", "stat=", "-o", "command=",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let pid = fields.next()?.parse().ok()?;
+                let parent = fields.next()?.parse().ok()?;
+                if fields.next()?.starts_with('Z') {
+                    return None;
+                }
+                Some(RunningProcess {
+                    process: Process {
+                        pid,
+                        command: fields.collect::<Vec<_>>().join(" "),
+                    },
+                    parent,
+                })
+            })
+            .collect(),
+    )
+}
+
+/// The processes started (directly or indirectly) by `pid`.
+#[cfg(unix)]
+fn descendants(pid: u32) -> Vec<Process> {
+    let running = running_processes().unwrap_or_default();
+    let mut descendants: Vec<Process> = Vec::new();
+    let mut parents = vec![pid];
+    while let Some(parent) = parents.pop() {
+        for child in running.iter().filter(|running| running.parent == parent) {
+            if !descendants.contains(&child.process) {
+                parents.push(child.process.pid);
+                descendants.push(child.process.clone());
+            }
+        }
+    }
+    descendants
+}
@@ -0,0 +1,330 @@
+use super::formatting;
+use super::suite::{CompileAndRunError, CompileAndRunResults, Outcome, SscceRunType};
+use crate::writeln_indented;
+use colored::Colorize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+const WELCOME_MESSAGE: &str = "Elm Torture - stress tests for an elm compiler";
+
+/// Something that presents the progress and results of an elm-torture run.
+///
+/// `suite_finished` is called from many threads as suites complete (in no
+/// particular order) and `finished` is called once with the results of every
+/// suite in the order the suites were found.
+pub trait Reporter<Ps>: Sync {
+    fn started(&self, suites: &[&Path]);
+    fn suite_finished(&self, result: &CompileAndRunResults<Ps>);
+    fn finished(&self, results: &[CompileAndRunResults<Ps>]);
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ReporterKind {
+    Pretty,
+    Tap,
+    GithubActions,
+}
+
+impl ReporterKind {
+    pub fn reporter<Ps: AsRef<Path>>(self) -> Box<dyn Reporter<Ps>> {
+        match self {
+            ReporterKind::Pretty => Box::new(Pretty),
+            ReporterKind::Tap => Box::new(Tap),
+            ReporterKind::GithubActions => Box::new(GithubActions),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidReporterKind(String);
+
+impl FromStr for ReporterKind {
+    type Err = InvalidReporterKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "pretty" => Self::Pretty,
+            "tap" => Self::Tap,
+            "github-actions" => Self::GithubActions,
+            _ => return Err(InvalidReporterKind(s.to_string())),
+        })
+    }
+}
+
+impl fmt::Display for InvalidReporterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid reporter: {} (expected one of pretty, tap or github-actions)",
+            self.0
+        )
+    }
+}
+
+/// Runs of a suite sorted by compiler, optimisation level and then flag set.
+fn sorted_runs(
+    result: &CompileAndRunResults<impl AsRef<Path>>,
+) -> Vec<(&SscceRunType, usize, Option<&CompileAndRunError>)> {
+    let mut runs: Vec<_> = result
+        .errors
+        .iter()
+        .map(|(run_type, (retries, error))| (run_type, *retries, error.as_ref()))
+        .collect();
+    runs.sort_by_key(|((compiler, opt_level, flag_set), _, _)| {
+        (compiler.to_string(), opt_level.id(), flag_set.clone())
+    });
+    runs
+}
+
+fn suite_list<'a>(suites: &'a [&'a Path]) -> impl fmt::Display + 'a {
+    formatting::easy_format(move |f| {
+        for path in suites {
+            writeln!(f, "{}", path.display())?;
+        }
+        Ok(())
+    })
+}
+
+/// The human readable, coloured output elm-torture has always produced.
+pub struct Pretty;
+
+impl<Ps: AsRef<Path>> Reporter<Ps> for Pretty {
+    fn started(&self, suites: &[&Path]) {
+        println!(
+            "{}
+
+Running the following {} SSCCE{}:
+{}
+",
+            WELCOME_MESSAGE,
+            suites.len(),
+            if suites.len() == 1 { "" } else { "s" },
+            indented::indented(suite_list(suites))
+        );
+    }
+
+    fn suite_finished(
+        &self,
+        CompileAndRunResults {
+            suite,
+            sscce_out_dir,
+            errors,
+        }: &CompileAndRunResults<Ps>,
+    ) {
+        let errors_to_print = errors.iter().filter_map(|(ol, (retries, me))| match me {
+            Some(
+                CompileAndRunError::RunFailure { allowed, .. }
+                | CompileAndRunError::CompileFailure { allowed, .. },
+            ) if *allowed => None,
+            e => e.as_ref().map(|ee| (ol, (retries, ee))),
+        });
+        for ((elm_compiler, opt_level, flag_set), (retries, e)) in errors_to_print {
+            println!(
+                "{} compiling with {} in {} optimisation mode{}\n{}",
+                suite.as_ref().display().to_string().black().on_white(),
+                elm_compiler.to_string().black().on_white(),
+                opt_level.to_string().black().on_white(),
+                formatting::with_flag_set(flag_set.as_deref()),
+                indented::indented(formatting::compile_and_run_error(
+                    e,
+                    suite,
+                    &sscce_out_dir,
+                    *retries
+                ))
+            );
+        }
+    }
+
+    fn finished(&self, suite_results: &[CompileAndRunResults<Ps>]) {
+        println!(
+            "
+elm-torture has run the following {} SSCCE{}:
+{}
+",
+            suite_results.len(),
+            if suite_results.len() == 1 { "" } else { "s" },
+            indented::indented(formatting::easy_format(|f| {
+                let mut opt_levels_of_interest = HashSet::new();
+                loop {
+                    let mut current_opt_level = None;
+                    for CompileAndRunResults { suite, errors, .. } in suite_results {
+                        for (sscce_run_type, (_, possible_error)) in errors {
+                            let (compiler, run_opt_level, flag_set) = sscce_run_type;
+                            let should_print = if let Some(ol) = current_opt_level {
+                                ol == sscce_run_type
+                            } else if opt_levels_of_interest.contains(sscce_run_type) {
+                                false
+                            } else {
+                                current_opt_level = Some(sscce_run_type);
+                                opt_levels_of_interest.insert(sscce_run_type);
+                                writeln!(
+                                    f,
+                                    "Compiling with {} in {} optimisation mode{}",
+                                    compiler.to_string().black().on_white(),
+                                    run_opt_level.to_string().black().on_white(),
+                                    formatting::with_flag_set(flag_set.as_deref()),
+                                )?;
+                                true
+                            };
+                            if should_print {
+                                writeln_indented!(
+                                    f,
+                                    "{} ({})",
+                                    suite.as_ref().display(),
+                                    match possible_error {
+                                        Some(CompileAndRunError::RunFailure {
+                                            allowed: true,
+                                            ..
+                                        }) => "allowed run failure".yellow(),
+                                        Some(CompileAndRunError::CompileFailure {
+                                            allowed: true,
+                                            ..
+                                        }) => "allowed compile failure".yellow(),
+                                        Some(CompileAndRunError::ExpectedCompileFailure) =>
+                                            "success when elm-torture expected a compile time failure"
+                                                .red(),
+                                        Some(CompileAndRunError::ExpectedRunFailure) =>
+                                            "success when elm-torture expected a run time failure"
+                                                .red(),
+                                        Some(_) => "failure".red(),
+                                        None => "success".green(),
+                                    }
+                                )?;
+                            }
+                        }
+                    }
+                    if current_opt_level.is_none() {
+                        break;
+                    }
+                }
+                Ok(())
+            }))
+        );
+    }
+}
+
+/// Output following version 13 of the Test Anything Protocol.
+///
+/// Every (suite, compiler, optimisation level, flag set) is one test point.
+/// Allowed failures are reported as skipped and the details of failures are
+/// included as a YAML diagnostic block.
+pub struct Tap;
+
+impl<Ps: AsRef<Path>> Reporter<Ps> for Tap {
+    fn started(&self, _suites: &[&Path]) {
+        println!("TAP version 13");
+    }
+
+    fn suite_finished(&self, _result: &CompileAndRunResults<Ps>) {}
+
+    fn finished(&self, results: &[CompileAndRunResults<Ps>]) {
+        let mut test_number = 0;
+        for result in results {
+            for ((compiler, opt_level, flag_set), retries, error) in sorted_runs(result) {
+                test_number += 1;
+                let description = format!(
+                    "{} ({}, {})",
+                    result.suite.as_ref().display(),
+                    compiler,
+                    formatting::run_id(*opt_level, flag_set.as_deref())
+                );
+                let outcome = Outcome::of(error);
+                match outcome {
+                    Outcome::Success => println!("ok {test_number} - {description}"),
+                    Outcome::AllowedFailure => println!(
+                        "ok {test_number} - {description} # SKIP {}",
+                        match error {
+                            Some(CompileAndRunError::CompileFailure { .. }) =>
+                                "allowed compile failure",
+                            _ => "allowed run failure",
+                        }
+                    ),
+                    Outcome::UnexpectedSuccess | Outcome::Failure => {
+                        println!("not ok {test_number} - {description}");
+                    }
+                }
+                if let Some(error) = error.filter(|_| outcome != Outcome::Success) {
+                    let message = formatting::without_escapes(
+                        &formatting::compile_and_run_error(
+                            error,
+                            &result.suite,
+                            &result.sscce_out_dir,
+                            retries,
+                        )
+                        .to_string(),
+                    );
+                    println!("  ---");
+                    println!("  outcome: {}", outcome.name());
+                    println!("  message: |-");
+                    for line in message.lines() {
+                        println!("    {line}");
+                    }
+                    println!("  ...");
+                }
+            }
+        }
+        println!("1..{test_number}");
+    }
+}
+
+/// The pretty output followed by a workflow command for every unexpected
+/// failure so that GitHub Actions annotates the suite's `Main.elm`.
+pub struct GithubActions;
+
+fn escape_workflow_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_workflow_property(s: &str) -> String {
+    escape_workflow_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+impl<Ps: AsRef<Path>> Reporter<Ps> for GithubActions {
+    fn started(&self, suites: &[&Path]) {
+        Reporter::<Ps>::started(&Pretty, suites);
+    }
+
+    fn suite_finished(&self, result: &CompileAndRunResults<Ps>) {
+        Pretty.suite_finished(result);
+    }
+
+    fn finished(&self, results: &[CompileAndRunResults<Ps>]) {
+        Pretty.finished(results);
+        for result in results {
+            for ((compiler, opt_level, flag_set), retries, error) in sorted_runs(result) {
+                if let (Outcome::UnexpectedSuccess | Outcome::Failure, Some(error)) =
+                    (Outcome::of(error), error)
+                {
+                    let file = result.suite.as_ref().join("Main.elm");
+                    let title = format!(
+                        "{} ({}, {})",
+                        result.suite.as_ref().display(),
+                        compiler,
+                        formatting::run_id(*opt_level, flag_set.as_deref())
+                    );
+                    let message = formatting::without_escapes(
+                        &formatting::compile_and_run_error(
+                            error,
+                            &result.suite,
+                            &result.sscce_out_dir,
+                            retries,
+                        )
+                        .to_string(),
+                    );
+                    println!(
+                        "::error file={},title={}::{}",
+                        escape_workflow_property(&file.display().to_string()),
+                        escape_workflow_property(&title),
+                        escape_workflow_data(&message)
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use log::debug;
+use serde_json::Value;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Make a scratch copy of `suite` in `build_dir` for a compiler to build.
+///
+/// Compilers write `elm-stuff` into the directory they build so building the
+/// suite in place would dirty (and race on) the source tree. Files are hard
+/// linked where possible as compilers never write to them. Anything left in
+/// `build_dir` by a previous build is removed first.
+///
+/// Source directories outside of the suite (such as `../../util`) are made
+/// absolute in the copied `elm.json` so that they still resolve.
+pub fn copy_suite(suite: &Path, build_dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(build_dir) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    fs::create_dir_all(build_dir)?;
+    debug!("Copying {} to {}", suite.display(), build_dir.display());
+    link_tree(suite, build_dir)?;
+    fs::write(
+        build_dir.join("elm.json"),
+        relocated_elm_json(suite, &fs::read(suite.join("elm.json"))?),
+    )
+}
+
+fn link_tree(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let to = to.join(&name);
+        if entry.file_type()?.is_dir() {
+            if name != OsStr::new("elm-stuff") {
+                fs::create_dir(&to)?;
+                link_tree(&entry.path(), &to)?;
+            }
+        } else if name != OsStr::new("elm.json") && fs::hard_link(entry.path(), &to).is_err() {
+            fs::copy(entry.path(), &to)?;
+        }
+    }
+    Ok(())
+}
+
+/// The source directory `dir` from a suite's `elm.json` if it lies outside of
+/// the suite (which must be canonical).
+fn external_source_dir(suite: &Path, dir: &Value) -> Option<PathBuf> {
+    dir.as_str()
+        .and_then(|dir| suite.join(dir).canonicalize().ok())
+        .filter(|absolute| !absolute.starts_with(suite))
+}
+
+/// The source directories of a suite that lie outside of it and so are not
+/// copied by `copy_suite`.
+pub fn external_source_dirs(suite: &Path) -> Vec<PathBuf> {
+    fn find(suite: &Path) -> Option<Vec<PathBuf>> {
+        let parsed: Value = serde_json::from_slice(&fs::read(suite.join("elm.json")).ok()?).ok()?;
+        let suite = suite.canonicalize().ok()?;
+        Some(
+            parsed
+                .get("source-directories")?
+                .as_array()?
+                .iter()
+                .filter_map(|dir| external_source_dir(&suite, dir))
+                .collect(),
+        )
+    }
+
+    find(suite).unwrap_or_default()
+}
+
+/// The contents of a copy of the suite's `elm.json` that can be built from
+/// another directory.
+///
+/// If the `elm.json` cannot be understood it is copied unchanged and the
+/// compiler is left to report any problems.
+fn relocated_elm_json(suite: &Path, elm_json: &[u8]) -> Vec<u8> {
+    fn relocate(suite: &Path, elm_json: &[u8]) -> Option<Vec<u8>> {
+        let mut parsed: Value = serde_json::from_slice(elm_json).ok()?;
+        let suite = suite.canonicalize().ok()?;
+        for source_dir in parsed.get_mut("source-directories")?.as_array_mut()? {
+            if let Some(absolute) = external_source_dir(&suite, source_dir) {
+                *source_dir = Value::String(absolute.to_string_lossy().into_owned());
+            }
+        }
+        serde_json::to_vec_pretty(&parsed).ok()
+    }
+
+    relocate(suite, elm_json).unwrap_or_else(|| elm_json.to_vec())
+}
@@ -1,16 +1,20 @@
 use futures::channel::oneshot;
 use log::info;
+use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 use std::net::SocketAddr;
 use tokio::runtime;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub(crate) enum Protocol {
     Http,
-    #[allow(unused)]
     Https,
 }
 
+/// The certificate used by HTTPS servers. Clients must trust it explicitly.
+pub(crate) const CERTIFICATE: &[u8] = include_bytes!("../../embed-assets/cert.pem");
+
 pub(crate) struct ServerId<'pool> {
     shutdown: Option<oneshot::Sender<()>>,
     pub url: SocketAddr,
@@ -54,7 +58,7 @@ impl ServerPool {
                 Protocol::Https => {
                     let (url, server) = warp::serve(filter)
                         .tls()
-                        .cert(include_bytes!("../../embed-assets/cert.pem"))
+                        .cert(CERTIFICATE)
                         .key(include_bytes!("../../embed-assets/key.key"))
                         .bind_with_graceful_shutdown(a, async {
                             rx.await.ok();
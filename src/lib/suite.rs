@@ -1,7 +1,16 @@
+use super::cache::CompileCache;
 use super::config;
+use super::elm_home::ElmHomes;
+use super::expectations::{self, ExpectationMismatch, LogsExpectation, PortExpectation, Recording};
+use super::find_suites;
+use super::network::{NetworkConfig, OriginMismatches, Server};
+use super::process_tree;
+use super::scratch;
 use super::server_pool::Protocol;
-use super::server_pool::ServerId;
 use super::server_pool::ServerPool;
+use super::server_pool::CERTIFICATE;
+use super::trace::Trace;
+use super::websocket::{WebSocketConfig, WebSocketMismatch, WebSocketServer};
 use anyhow::bail;
 use anyhow::Context;
 use apply::{Also, Apply};
@@ -13,15 +22,20 @@ use log::debug;
 use rayon::prelude::*;
 use serde::Deserialize;
 use serde::Serialize;
+use serde::Serializer;
 use serde_json::json;
 use serde_json::map::Entry;
 use serde_json::Map;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::env;
 use std::marker::PhantomData;
-use std::net::SocketAddr;
+use std::mem;
 use std::net::ToSocketAddrs;
 use std::process::{Output, Stdio};
+use std::slice;
 use std::sync::Arc;
+use std::thread;
 use std::{collections::HashMap, fs::File};
 use std::{fs, sync::Mutex};
 use std::{io, process::Command};
@@ -33,7 +47,6 @@ use std::{
     time::Duration,
 };
 use wait_timeout::ChildExt;
-use warp::Filter;
 
 type AnyOneOf<T> = Option<Box<[T]>>;
 
@@ -51,11 +64,14 @@ impl<T> AnyOneOfExt for AnyOneOf<T> {
 
 fn run_until_success<T, E>(
     max_retries: usize,
+    should_retry: impl Fn(&E) -> bool,
     mut f: impl FnMut() -> Result<T, E>,
 ) -> (usize, Result<T, E>) {
     for i in 0..max_retries {
-        if let Ok(val) = f() {
-            return (i, Ok(val));
+        match f() {
+            Ok(val) => return (i, Ok(val)),
+            Err(e) if !should_retry(&e) => return (i, Err(e)),
+            Err(_) => {}
         }
     }
     (max_retries, f())
@@ -71,8 +87,10 @@ fn iter_pairs<T: Clone + Sync + Send, U: Send>(
         .flat_map(move |v1| it2.clone().map(move |v2| (v1.clone(), v2)))
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct ElmCompilerPath {
+    #[serde(rename = "name")]
     unresolved: String,
     path: PathBuf,
     pub stdlib_variant: StdlibVariant,
@@ -85,6 +103,26 @@ impl fmt::Display for ElmCompilerPath {
 }
 
 impl ElmCompilerPath {
+    /// The name of the compiler with anything that might not be allowed in a
+    /// file name replaced.
+    fn file_name_safe(&self) -> String {
+        self.unresolved
+            .trim_start_matches(|c: char| !c.is_alphanumeric())
+            .replace(|c: char| !c.is_alphanumeric() && c != '-' && c != '_', "-")
+    }
+
+    /// Identifies the output of compiling with this compiler at `opt_level`.
+    ///
+    /// Compilers build a suite concurrently so any files they write must be
+    /// named with this.
+    fn build_id(&self, opt_level: OptimizationLevel) -> String {
+        format!("{}-{}", opt_level.id(), self.file_name_safe())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     fn new_resolved(binary_name: String) -> Result<Self, DetectStdlibError> {
         use bstr::ByteSlice;
         let path = which::which(&binary_name).map_err(DetectStdlibError::LocatingCompiler)?;
@@ -117,43 +155,89 @@ impl ElmCompilerPath {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum PortType {
     Command,
     Subscription,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case", tag = "method")]
-pub enum Request {
-    Get { url: String },
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case", transparent)]
-pub struct Response(String);
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct PortName(String);
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
-pub struct NetworkItem {
-    request: Request,
-    response: Response,
-}
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct PortArg(pub serde_json::Value);
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct PortName(String);
+pub type PortEvent = (PortType, PortName, PortArg);
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct PortArg(serde_json::Value);
+/// Show a port event the way it is written in a suite's config.
+pub fn display_port_event((port_type, PortName(name), PortArg(arg)): &PortEvent) -> String {
+    serde_json::to_string(&(port_type, name, arg)).expect("port events are always valid json")
+}
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(transparent)]
 pub struct Flags<Readiness>(
     serde_json::Map<String, serde_json::Value>,
     PhantomData<Readiness>,
 );
 
+/// The `flags` of a suite's config: either the flags to run the suite with or
+/// a list of named flag sets, the suite being run once with each.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged, try_from = "serde_json::Value")]
+pub enum FlagsConfig<Readiness> {
+    Sets(Box<[FlagSet]>),
+    Single(Flags<Readiness>),
+}
+
+/// A named set of flags along with the port events and logs expected when
+/// the suite is run with them (if they differ from the rest of the suite's
+/// config).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FlagSet {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<Flags<Raw>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ports: Option<Box<[PortExpectation]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logs: Option<LogsExpectation>,
+}
+
+// Deserialized by hand so that errors within flag sets are not hidden behind
+// "data did not match any variant".
+impl<Readiness> TryFrom<serde_json::Value> for FlagsConfig<Readiness> {
+    type Error = String;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        if !value.is_array() {
+            return serde_json::from_value(value)
+                .map(Self::Single)
+                .map_err(|e| e.to_string());
+        }
+        let sets: Box<[FlagSet]> = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        let mut names = HashSet::new();
+        for FlagSet { name, .. } in &*sets {
+            // Names are used in the names of files in the out dir.
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "Flag set name {name:?} must only contain letters, digits, '-' and '_'"
+                ));
+            }
+            if !names.insert(name) {
+                return Err(format!("Flag set name {name:?} is used more than once"));
+            }
+        }
+        Ok(Self::Sets(sets))
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Copy, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub enum StdlibVariant {
@@ -169,29 +253,30 @@ pub enum Platform {
     Windows,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct RunFailsIfAll {
     stdlib_variant: AnyOneOf<StdlibVariant>,
     opt_level: AnyOneOf<config::OptimizationLevel>,
     platform: AnyOneOf<Platform>,
+    flag_set: AnyOneOf<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct CompileFailsIfAll {
     opt_level: AnyOneOf<config::OptimizationLevel>,
     platform: AnyOneOf<Platform>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(untagged, rename_all = "kebab-case")]
 pub enum ConditionCollection<C> {
     Collection(ConditionCollectionHelper<C>),
     Cond(C),
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub enum ConditionCollectionHelper<C> {
     All(Box<[ConditionCollection<C>]>),
@@ -225,6 +310,7 @@ struct RunFailsIfAllFacts {
     opt_level: config::OptimizationLevel,
     stdlib_variant: StdlibVariant,
     platform: Platform,
+    flag_set: Option<String>,
 }
 
 impl Condition for RunFailsIfAll {
@@ -235,6 +321,7 @@ impl Condition for RunFailsIfAll {
                 .stdlib_variant
                 .any(|variant| *variant == f.stdlib_variant)
             && self.platform.any(|platform| *platform == f.platform)
+            && self.flag_set.any(|name| Some(name) == f.flag_set.as_ref())
     }
 }
 struct CompileFailsIfAllFacts {
@@ -250,80 +337,239 @@ impl Condition for CompileFailsIfAll {
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
 struct Ready;
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
 struct Raw;
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct Config<Readiness> {
     #[serde(skip_serializing_if = "Option::is_none")]
-    ports: Option<Box<[(PortType, PortName, PortArg)]>>,
+    ports: Option<Box<[PortExpectation]>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    flags: Option<Flags<Readiness>>,
+    flags: Option<FlagsConfig<Readiness>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    network: Option<Arc<[NetworkItem]>>,
+    network: Option<NetworkConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    logs: Option<String>,
+    network_protocol: Option<Protocol>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    websocket: Option<Arc<WebSocketConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logs: Option<LogsExpectation>,
     #[serde(skip_serializing_if = "Option::is_none")]
     compile_fails_if: Option<ConditionCollection<CompileFailsIfAll>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     run_fails_if: Option<ConditionCollection<RunFailsIfAll>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     skip_run_if: Option<ConditionCollection<RunFailsIfAll>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<Box<[String]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overrides: Option<Box<[Override]>>,
+    #[serde(skip)]
+    sources: Sources,
+}
+
+/// Where in the suite's config each of the expectations came from (as a JSON
+/// pointer, `None` being the top level) so that blessing can update them
+/// there.
+#[derive(Debug, Default, Clone)]
+struct Sources {
+    ports: Option<String>,
+    logs: Option<String>,
+}
+
+/// An entry in the `overrides` list of a suite's config. If the condition
+/// holds for a run then the `ports`, `logs` and `flags` given here replace
+/// those of the suite's config.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Override {
+    #[serde(rename = "if")]
+    condition: ConditionCollection<RunFailsIfAll>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ports: Option<Box<[PortExpectation]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logs: Option<LogsExpectation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<Flags<Raw>>,
 }
 
 impl Config<Raw> {
+    /// The names of the flag sets to run the suite with (or just `None` if
+    /// the suite does not have flag sets).
+    fn flag_sets(&self) -> Vec<Option<String>> {
+        match &self.flags {
+            Some(FlagsConfig::Sets(sets)) => {
+                sets.iter().map(|set| Some(set.name.clone())).collect()
+            }
+            _ => vec![None],
+        }
+    }
+
+    /// Use the flag set called `name` (if the suite has flag sets), along
+    /// with any expectations it has.
+    fn select_flag_set(mut self, name: Option<&str>) -> Self {
+        if let Some(FlagsConfig::Sets(sets)) = &mut self.flags {
+            let (i, set) = mem::take(sets)
+                .into_vec()
+                .into_iter()
+                .enumerate()
+                .find(|(_, set)| Some(set.name.as_str()) == name)
+                .expect("flag set names come from the suite's config");
+            self.flags = set.flags.map(FlagsConfig::Single);
+            self.ports = set.ports.or(self.ports);
+            self.logs = set.logs.or(self.logs);
+            // Bless each flag set's expectations separately.
+            let pointer = format!("/flags/{i}");
+            self.sources.ports = Some(pointer.clone());
+            self.sources.logs = Some(pointer);
+        }
+        self
+    }
+
+    /// Apply the overrides whose conditions hold for this run, in the order
+    /// they are listed (so later overrides win).
+    fn apply_overrides(mut self, facts: &RunFailsIfAllFacts) -> Self {
+        for (i, o) in self
+            .overrides
+            .take()
+            .unwrap_or_default()
+            .into_vec()
+            .into_iter()
+            .enumerate()
+        {
+            if o.condition.is_met(facts) {
+                if o.ports.is_some() {
+                    self.ports = o.ports;
+                    self.sources.ports = Some(format!("/overrides/{i}"));
+                }
+                if o.logs.is_some() {
+                    self.logs = o.logs;
+                    self.sources.logs = Some(format!("/overrides/{i}"));
+                }
+                if let Some(flags) = o.flags {
+                    self.flags = Some(FlagsConfig::Single(flags));
+                }
+            }
+        }
+        self
+    }
+
     fn make_ready(
         self,
-        url_and_protocol: Option<(impl AsRef<str>, impl AsRef<str>)>,
+        servers: &[Server],
+        websocket_server: Option<&WebSocketServer>,
     ) -> anyhow::Result<Config<Ready>> {
         let Self {
             ports,
             flags,
             network,
+            network_protocol,
+            websocket,
             logs,
             compile_fails_if,
             run_fails_if,
             skip_run_if,
+            tags,
+            overrides: _,
+            sources,
         } = self;
 
-        let mut flags = flags.map_or_else(Map::new, |Flags(flags, _)| flags);
+        let mut flags = match flags {
+            None => Map::new(),
+            Some(FlagsConfig::Single(Flags(flags, _))) => flags,
+            Some(FlagsConfig::Sets(_)) => bail!("A flag set must be selected first!"),
+        };
         match flags.entry("suite") {
             Entry::Occupied(_) => {
                 bail!("Flags cannot have the key suite (it is reserved for suite params)!");
             }
             Entry::Vacant(v) => {
-                if let Some((url, protocol)) = url_and_protocol {
-                    v.insert(json!({
-                        "url": url.as_ref(),
-                        "protocol":protocol.as_ref()
-                    }));
+                let mut suite = Map::new();
+                let mut origins = Map::new();
+                for server in servers {
+                    let url = json!(server.url().to_string());
+                    let protocol = json!(server.protocol());
+                    if let Some(origin) = server.origin() {
+                        origins.insert(
+                            origin.to_string(),
+                            json!({ "url": url, "protocol": protocol }),
+                        );
+                    } else {
+                        suite.insert("url".to_string(), url);
+                        suite.insert("protocol".to_string(), protocol);
+                    }
+                }
+                if !origins.is_empty() {
+                    suite.insert("origins".to_string(), json!(origins));
+                }
+                if let Some(websocket_server) = websocket_server {
+                    suite.insert(
+                        "websocket".to_string(),
+                        json!({ "url": websocket_server.url() }),
+                    );
+                }
+                if !suite.is_empty() {
+                    v.insert(json!(suite));
                 }
             }
         }
         Ok(Config {
-            flags: Some(Flags(flags, PhantomData)),
+            flags: Some(FlagsConfig::Single(Flags(flags, PhantomData))),
             ports,
             network,
+            network_protocol,
+            websocket,
             logs,
             compile_fails_if,
             run_fails_if,
             skip_run_if,
+            tags,
+            overrides: None,
+            sources,
         })
     }
 }
 
-#[derive(Debug)]
+fn serialize_display<S: Serializer>(value: &impl fmt::Display, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(value)
+}
+
+fn serialize_lossy<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&String::from_utf8_lossy(bytes))
+}
+
+fn serialize_output<S: Serializer>(output: &Output, s: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeStruct;
+    let mut state = s.serialize_struct("Output", 3)?;
+    state.serialize_field("exit-code", &output.status.code())?;
+    state.serialize_field("stdout", &String::from_utf8_lossy(&output.stdout))?;
+    state.serialize_field("stderr", &String::from_utf8_lossy(&output.stderr))?;
+    state.end()
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "type", content = "details")]
 pub enum CompileError {
-    Process(io::Error),
-    Compiler(Output),
-    CompilerStdErrNotEmpty(Output),
-    ReadingTargets(io::Error),
-    DeletingElmStuff(io::Error),
+    Process(#[serde(serialize_with = "serialize_display")] io::Error),
+    Compiler(#[serde(serialize_with = "serialize_output")] Output),
+    CompilerStdErrNotEmpty(#[serde(serialize_with = "serialize_output")] Output),
+    ReadingTargets(#[serde(serialize_with = "serialize_display")] io::Error),
+    CopyingSuite(#[serde(serialize_with = "serialize_display")] io::Error),
+    PreparingElmHome(#[serde(serialize_with = "serialize_display")] io::Error),
     SuiteDoesNotExist,
+    #[serde(rename_all = "kebab-case")]
+    Timeout {
+        #[serde(serialize_with = "humantime_serde::serialize")]
+        after: Duration,
+        #[serde(serialize_with = "serialize_lossy")]
+        stdout: Vec<u8>,
+        #[serde(serialize_with = "serialize_lossy")]
+        stderr: Vec<u8>,
+    },
 }
 
 #[derive(Debug)]
@@ -332,32 +578,52 @@ pub enum DetectStdlibError {
     LocatingCompiler(which::Error),
     Parsing(Box<[u8]>),
 }
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "type", content = "details")]
 pub enum GetSuiteConfigError {
-    CannotRead(io::Error),
-    Parse(serde_json::Error),
+    CannotRead(#[serde(serialize_with = "serialize_display")] io::Error),
+    Parse(#[serde(serialize_with = "serialize_display")] serde_json::Error),
 }
 
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "type", content = "details")]
 pub enum RunError {
-    NodeNotFound(which::Error),
+    NodeNotFound(#[serde(serialize_with = "serialize_display")] which::Error),
     SuiteDoesNotExist,
-    NodeProcess(io::Error),
-    WritingHarness(io::Error),
-    CopyingExpectedOutput(io::Error),
-    Runtime(Output),
-    WritingExpectedOutput(io::Error),
-    ExpectedOutputNotUtf8(string::FromUtf8Error),
-    OutputProduced(Output),
+    NodeProcess(#[serde(serialize_with = "serialize_display")] io::Error),
+    WritingHarness(#[serde(serialize_with = "serialize_display")] io::Error),
+    CopyingExpectedOutput(#[serde(serialize_with = "serialize_display")] io::Error),
+    #[serde(rename_all = "kebab-case")]
+    Runtime {
+        #[serde(serialize_with = "serialize_output")]
+        output: Output,
+        trace: Option<Trace>,
+    },
+    WritingExpectedOutput(#[serde(serialize_with = "serialize_display")] io::Error),
+    Blessing(#[serde(serialize_with = "serialize_display")] io::Error),
+    ReadingRecording(#[serde(serialize_with = "serialize_display")] io::Error),
+    ExpectationMismatch(ExpectationMismatch),
+    Network(Box<[OriginMismatches]>),
+    WebSocket(Box<[WebSocketMismatch]>),
+    ExpectedOutputNotUtf8(#[serde(serialize_with = "serialize_display")] string::FromUtf8Error),
+    OutputProduced(#[serde(serialize_with = "serialize_output")] Output),
+    #[serde(rename_all = "kebab-case")]
     Timeout {
+        #[serde(serialize_with = "humantime_serde::serialize")]
         after: Duration,
+        #[serde(serialize_with = "serialize_lossy")]
         stdout: Vec<u8>,
+        #[serde(serialize_with = "serialize_lossy")]
         stderr: Vec<u8>,
+        trace: Option<Trace>,
+        /// Processes started by the suite that could not be stopped.
+        still_running: Box<[process_tree::Process]>,
     },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "type", content = "details")]
 pub enum CompileAndRunError {
     SuiteNotExist,
     SuiteNotDir,
@@ -374,7 +640,48 @@ pub enum CompileAndRunError {
     },
     ExpectedCompileFailure,
     ExpectedRunFailure,
-    Server(anyhow::Error),
+    Server(#[serde(serialize_with = "serialize_display")] anyhow::Error),
+}
+
+/// How the result of compiling and running an SSCCE should be reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    AllowedFailure,
+    UnexpectedSuccess,
+    Failure,
+}
+
+impl Outcome {
+    pub fn of(error: Option<&CompileAndRunError>) -> Self {
+        match error {
+            None => Self::Success,
+            Some(
+                CompileAndRunError::CompileFailure { allowed: true, .. }
+                | CompileAndRunError::RunFailure { allowed: true, .. },
+            ) => Self::AllowedFailure,
+            Some(
+                CompileAndRunError::ExpectedCompileFailure | CompileAndRunError::ExpectedRunFailure,
+            ) => Self::UnexpectedSuccess,
+            Some(_) => Self::Failure,
+        }
+    }
+
+    /// The name of the outcome as used by every report.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::AllowedFailure => "allowed-failure",
+            Self::UnexpectedSuccess => "unexpected-success",
+            Self::Failure => "failure",
+        }
+    }
+}
+
+impl Serialize for Outcome {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
 }
 
 fn set_elm_home(command: &mut Command) {
@@ -383,28 +690,82 @@ fn set_elm_home(command: &mut Command) {
     }
 }
 
+/// Read from `read` until it is closed on a new thread.
+///
+/// Output is read as it is produced so that it is available even if the
+/// process producing it has to be stopped.
+fn read_in_background(
+    mut read: impl io::Read + Send + 'static,
+) -> (Arc<Mutex<Vec<u8>>>, thread::JoinHandle<()>) {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let writer = Arc::clone(&buffer);
+    let handle = thread::spawn(move || {
+        let mut chunk = [0; 4096];
+        while let Ok(n) = read.read(&mut chunk) {
+            if n == 0 {
+                break;
+            }
+            writer.lock().unwrap().extend_from_slice(&chunk[..n]);
+        }
+    });
+    (buffer, handle)
+}
+
+fn take_buffer(buffer: &Mutex<Vec<u8>>) -> Vec<u8> {
+    mem::take(&mut *buffer.lock().unwrap())
+}
+
+/// Like `Command::output` but stops the compiler if it takes longer than
+/// `timeout`.
+fn output_with_timeout(command: &mut Command, timeout: Duration) -> Result<Output, CompileError> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(CompileError::Process)?;
+    let (stdout, stdout_reader) = read_in_background(child.stdout.take().unwrap());
+    let (stderr, stderr_reader) = read_in_background(child.stderr.take().unwrap());
+    if let Some(status) = child.wait_timeout(timeout).map_err(CompileError::Process)? {
+        let _ = stdout_reader.join();
+        let _ = stderr_reader.join();
+        Ok(Output {
+            status,
+            stdout: take_buffer(&stdout),
+            stderr: take_buffer(&stderr),
+        })
+    } else {
+        child.kill().map_err(CompileError::Process)?;
+        child.wait().map_err(CompileError::Process)?;
+        // Any processes started by the compiler may still hold the pipes open
+        // so do not wait for the readers to finish.
+        Err(CompileError::Timeout {
+            after: timeout,
+            stdout: take_buffer(&stdout),
+            stderr: take_buffer(&stderr),
+        })
+    }
+}
+
+/// Compile a scratch copy of `suite` in `build_dir` so that the suite itself
+/// is never written to.
 fn compile(
     suite: &Path,
+    build_dir: &Path,
     out_file: impl AsRef<Path>,
-    compiler_lock: &Mutex<()>,
+    elm_homes: &ElmHomes,
     opt_level: OptimizationLevel,
     compiler_path: &ElmCompilerPath,
     config: &config::Config,
 ) -> (usize, Result<(), CompileError>) {
     fn compile_help(
-        suite: impl AsRef<Path>,
+        suite: &Path,
+        build_dir: &Path,
         command: &mut Command,
+        timeout: Duration,
     ) -> Result<Output, CompileError> {
-        fs::remove_dir_all(suite.as_ref().join("elm-stuff"))
-            .or_else(|e| {
-                if e.kind() == io::ErrorKind::NotFound {
-                    Ok(())
-                } else {
-                    Err(e)
-                }
-            })
-            .map_err(CompileError::DeletingElmStuff)?;
-        let output = command.output().map_err(CompileError::Process)?;
+        scratch::copy_suite(suite, build_dir).map_err(CompileError::CopyingSuite)?;
+        let output = output_with_timeout(command, timeout)?;
 
         if !output.status.success() {
             return Err(CompileError::Compiler(output));
@@ -434,20 +795,26 @@ fn compile(
     };
     let mut command = compiler_path.command();
 
-    command.current_dir(suite);
+    command.current_dir(build_dir);
     command.arg("make");
     command.args(root_files);
     command.args(opt_level.args().iter());
     command.arg("--output");
-    set_elm_home(&mut command);
     command.arg(out_file.as_ref());
+    match elm_homes.for_current_thread() {
+        Ok(elm_home) => command.env("ELM_HOME", elm_home),
+        Err(e) => return (0, Err(CompileError::PreparingElmHome(e))),
+    };
 
     debug!("Invoking compiler: {:?}", command);
 
-    let (retries, _) = match run_until_success(config.compiler_max_retries(), || {
-        let _lock = compiler_lock.lock();
-        compile_help(&suite, &mut command)
-    }) {
+    // A compiler that hangs once will likely hang again so do not wait for it
+    // more than once.
+    let (retries, _) = match run_until_success(
+        config.compiler_max_retries(),
+        |e| !matches!(e, CompileError::Timeout { .. }),
+        || compile_help(suite, build_dir, &mut command, config.compile_timeout()),
+    ) {
         (r, Ok(op)) => (r, op),
         (r, Err(e)) => return (r, Err(e)),
     };
@@ -465,47 +832,110 @@ fn get_suite_config(suite: impl AsRef<Path>) -> Result<Config<Raw>, GetSuiteConf
     .map_err(GetSuiteConfigError::Parse)
 }
 
+/// The tags listed in a suite's `output.json`.
+pub fn get_suite_tags(suite: impl AsRef<Path>) -> Result<Box<[String]>, GetSuiteConfigError> {
+    get_suite_config(suite).map(|config| config.tags.unwrap_or_default())
+}
+
+/// Write a file that is shared by every run of a suite.
+///
+/// Other runs may be reading the file so it is replaced in one step rather
+/// than truncated and rewritten.
+fn write_shared(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file =
+        tempfile::NamedTempFile::new_in(path.parent().unwrap_or_else(|| Path::new(".")))?;
+    file.write_all(contents)?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// How long a suite that has timed out is given to exit after being asked to
+/// stop before it is killed.
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
 #[allow(clippy::too_many_lines)]
 fn run(
     suite: &Path,
     out_dir: &Path,
-    opt_level: OptimizationLevel,
+    build_id: &str,
+    flag_set: Option<&str>,
     config: &config::Config,
     suite_config: &Config<Ready>,
+    bless: bool,
 ) -> Result<(), RunError> {
-    fn read_to_buf(mut read: impl io::Read) -> io::Result<Vec<u8>> {
-        let mut buffer = Vec::new();
-
-        // read the whole file
-        read.read_to_end(&mut buffer)?;
-        Ok(buffer)
-    }
-
     if !suite.join("elm.json").exists() {
         return Err(RunError::SuiteDoesNotExist);
     }
     let node_exe = which::which(&config.node()).map_err(RunError::NodeNotFound)?;
     let harness_file = out_dir.join("harness.js");
     let xml_http_request_file = out_dir.join("xmlhttprequest.js");
-    let output_file = out_dir.join("output.json");
-    let main_file = out_dir.join("main.js");
-
-    fs::write(
+    let websocket_file = out_dir.join("websocket.js");
+    // Each build and flag set gets its own files so that runs do not clobber
+    // each other.
+    let run_id = flag_set.map_or_else(|| build_id.to_string(), |name| format!("{build_id}-{name}"));
+    let output_file_name = format!("output-{run_id}.json");
+    let output_file = out_dir.join(&output_file_name);
+    let main_file = out_dir.join(format!("main-{run_id}.js"));
+    let certificate_file = out_dir.join("cert.pem");
+    let recording_file_name = format!("recording-{run_id}.json");
+    let recording_file = out_dir.join(&recording_file_name);
+    let trace_file_name = format!("trace-{run_id}.jsonl");
+    let trace_file = out_dir.join(&trace_file_name);
+
+    write_shared(
         &harness_file,
         &include_bytes!("../../embed-assets/run.js")[..],
     )
     .map_err(RunError::WritingHarness)?;
-    fs::write(
+    write_shared(
         &xml_http_request_file,
         &include_bytes!("../../embed-assets/elm-serverless/src-bridge/xmlhttprequest.js")[..],
     )
     .map_err(RunError::WritingHarness)?;
+    write_shared(
+        &websocket_file,
+        &include_bytes!("../../embed-assets/websocket.js")[..],
+    )
+    .map_err(RunError::WritingHarness)?;
     fs::write(
         &output_file,
         &serde_json::to_vec_pretty(&suite_config).expect("Failed to reserialize output json"),
     )
     .map_err(RunError::WritingExpectedOutput)?;
 
+    // The harness appends to the trace so remove any left by a previous run.
+    if let Err(e) = fs::remove_file(&trace_file) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(RunError::WritingHarness(e));
+        }
+    }
+    let read_trace = || {
+        Trace::read(
+            &trace_file,
+            expectations::expected_port_events(suite_config.ports.as_deref().unwrap_or_default()),
+        )
+        .ok()
+    };
+
+    let mut harness_options = vec![
+        format!(
+            "recordFile: require('path').join(__dirname, '{}')",
+            recording_file_name
+        ),
+        format!(
+            "traceFile: require('path').join(__dirname, '{}')",
+            trace_file_name
+        ),
+        format!("generatedFile: require.resolve('./elm-{}.js')", build_id),
+    ];
+    if suite_config
+        .network
+        .iter()
+        .any(NetworkConfig::holds_responses)
+    {
+        harness_options.push("notifyPortEvents: true".to_string());
+    }
+
     File::create(&main_file)
         .map_err(RunError::WritingHarness)?
         .apply(|mut f| {
@@ -515,19 +945,28 @@ fn run(
 const harness = require('./harness.js');
 global.XMLHttpRequest = require('./xmlhttprequest.js').XMLHttpRequest;
 const generated = require('./elm-{}.js');
-const expectedOutput = require('./output.json');
+const expectedOutput = require('./{}');
 
-harness(generated, expectedOutput);
+harness(generated, expectedOutput, {{ {} }});
 "#,
-                opt_level.id()
+                build_id,
+                output_file_name,
+                harness_options.join(", ")
             )
         })
         .map_err(RunError::WritingHarness)?;
 
+    let mut command = Command::new(node_exe);
+
+    if suite_config.network_protocol == Some(Protocol::Https) {
+        write_shared(&certificate_file, CERTIFICATE).map_err(RunError::WritingHarness)?;
+        command.env("NODE_EXTRA_CA_CERTS", &certificate_file);
+    }
+
     // We pick a timezone **without** changes in offset for consistent testing.
     let tz = "Asia/Bahrain";
 
-    let mut runner_child = Command::new(node_exe)
+    let mut runner_child = process_tree::new_process_group(&mut command)
         .arg("--unhandled-rejections=strict")
         .arg(&main_file)
         .env("TZ", tz)
@@ -536,28 +975,33 @@ harness(generated, expectedOutput);
         .stderr(Stdio::piped())
         .spawn()
         .map_err(RunError::NodeProcess)?;
+    let (stdout, stdout_reader) = read_in_background(runner_child.stdout.take().unwrap());
+    let (stderr, stderr_reader) = read_in_background(runner_child.stderr.take().unwrap());
 
     let runner_status = runner_child
         .wait_timeout(config.run_timeout())
         .map_err(RunError::NodeProcess)?
         .map_or_else(
             || {
-                runner_child.kill().map_err(RunError::NodeProcess)?;
-                let stdout = read_to_buf(runner_child.stdout.as_mut().unwrap())
-                    .map_err(RunError::NodeProcess)?;
-                let stderr = read_to_buf(runner_child.stderr.as_mut().unwrap())
+                let still_running = process_tree::kill(&mut runner_child, KILL_GRACE_PERIOD)
                     .map_err(RunError::NodeProcess)?;
+                // Processes that could not be stopped may still hold the pipes
+                // open so do not wait for the readers to finish.
                 Err(RunError::Timeout {
                     after: config.run_timeout(),
-                    stdout,
-                    stderr,
+                    stdout: take_buffer(&stdout),
+                    stderr: take_buffer(&stderr),
+                    trace: read_trace(),
+                    still_running,
                 })
             },
             Ok,
         )?;
 
-    let stdout = read_to_buf(runner_child.stdout.unwrap()).map_err(RunError::NodeProcess)?;
-    let stderr = read_to_buf(runner_child.stderr.unwrap()).map_err(RunError::NodeProcess)?;
+    let _ = stdout_reader.join();
+    let _ = stderr_reader.join();
+    let stdout = take_buffer(&stdout);
+    let stderr = take_buffer(&stderr);
 
     let output = Output {
         status: runner_status,
@@ -566,7 +1010,10 @@ harness(generated, expectedOutput);
     };
 
     if !output.status.success() {
-        return Err(RunError::Runtime(output));
+        return Err(RunError::Runtime {
+            output,
+            trace: read_trace(),
+        });
     }
     if !output.stdout.is_empty() {
         return Err(RunError::OutputProduced(output));
@@ -584,18 +1031,103 @@ harness(generated, expectedOutput);
         return Err(RunError::OutputProduced(output));
     }
 
-    Ok(())
+    let recording = Recording::read(&recording_file).map_err(RunError::ReadingRecording)?;
+    let expected_ports = suite_config.ports.as_deref().unwrap_or_default();
+    let checked = expectations::check(
+        expected_ports,
+        suite_config
+            .logs
+            .as_ref()
+            .unwrap_or(&LogsExpectation::default()),
+        &recording,
+    );
+    if bless {
+        // Keep any of the suite's expectations (and so any unordered groups
+        // or matchers in them) that the run met.
+        let (keep_ports, keep_logs) = checked.map_or_else(
+            |mismatch| (mismatch.ports.is_none(), mismatch.logs.is_none()),
+            |()| (true, true),
+        );
+        bless_suite_config(
+            suite,
+            recording,
+            keep_ports,
+            keep_logs,
+            &suite_config.sources,
+        )
+        .map_err(RunError::Blessing)
+    } else {
+        checked.map_err(RunError::ExpectationMismatch)
+    }
 }
 
-pub type SscceRunType = (ElmCompilerPath, OptimizationLevel);
+/// Replace the `ports` (unless `keep_ports` is true) and `logs` (unless
+/// `keep_logs` is true) of the suite's `output.json` with those recorded by
+/// the harness, keeping every other key. Expectations that came from a flag
+/// set or an override are replaced there.
+///
+/// Comments in `output.json` are not preserved.
+fn bless_suite_config(
+    suite: &Path,
+    Recording { ports, logs }: Recording,
+    keep_ports: bool,
+    keep_logs: bool,
+    sources: &Sources,
+) -> io::Result<()> {
+    fn target<'a>(
+        config: &'a mut serde_json::Value,
+        source: Option<&str>,
+    ) -> io::Result<&'a mut Map<String, serde_json::Value>> {
+        config
+            .pointer_mut(source.unwrap_or_default())
+            .and_then(serde_json::Value::as_object_mut)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("output.json has no object at {source:?}"),
+                )
+            })
+    }
+
+    let config_path = suite.join("output.json");
+    let mut config: serde_json::Value =
+        serde_json::from_reader(StripComments::new(fs::read(&config_path)?.as_slice()))?;
+    if !keep_ports {
+        target(&mut config, sources.ports.as_deref())?
+            .insert("ports".to_string(), serde_json::to_value(ports)?);
+    }
+    if !keep_logs {
+        let target = target(&mut config, sources.logs.as_deref())?;
+        // Empty logs must be explicit to replace those of the rest of the
+        // config.
+        if logs.is_empty() && sources.logs.is_none() {
+            target.remove("logs");
+        } else {
+            target.insert("logs".to_string(), serde_json::Value::String(logs));
+        }
+    }
+    let mut contents = Vec::new();
+    config.serialize(&mut serde_json::Serializer::with_formatter(
+        &mut contents,
+        serde_json::ser::PrettyFormatter::with_indent(b"    "),
+    ))?;
+    contents.push(b'\n');
+    fs::write(config_path, contents)
+}
+
+/// A compiler, an optimisation level and (for suites with flag sets) the name
+/// of a flag set.
+pub type SscceRunType = (ElmCompilerPath, OptimizationLevel, Option<String>);
 
 #[allow(clippy::too_many_lines)]
 fn compile_and_run(
     suite: impl AsRef<Path> + Sync,
     out_dir: impl AsRef<Path> + Sync,
-    compiler_lock: &Mutex<()>,
-    configurations: impl IntoParallelIterator<Item = SscceRunType>,
+    elm_homes: &ElmHomes,
+    cache: Option<&CompileCache>,
+    configurations: impl IntoParallelIterator<Item = (ElmCompilerPath, OptimizationLevel)>,
     config: &config::Config,
+    bless: bool,
 ) -> HashMap<SscceRunType, (usize, Result<(), CompileAndRunError>)> {
     let platform = match env::consts::OS {
         "linux" => Platform::Linux,
@@ -606,52 +1138,26 @@ fn compile_and_run(
     let server_pool = ServerPool::new().unwrap();
     configurations
         .into_par_iter()
-        .map(|(elm_compiler, opt_level)| {
-            let res: (_, _) = crossbeam::scope(|_| {
+        .flat_map(|(elm_compiler, opt_level)| {
+            // Failures before the suite is run apply to every flag set and so
+            // are not reported against any one of them.
+            let res: Vec<(Option<String>, (usize, _))> = crossbeam::scope(|_| {
+                let before_run = |retries, e| vec![(None, (retries, Err(e)))];
                 if !suite.as_ref().exists() {
-                    return (0, Err(CompileAndRunError::SuiteNotExist));
+                    return before_run(0, CompileAndRunError::SuiteNotExist);
                 }
                 if !suite.as_ref().is_dir() {
-                    return (0, Err(CompileAndRunError::SuiteNotDir));
+                    return before_run(0, CompileAndRunError::SuiteNotDir);
                 }
                 if !suite.as_ref().join("elm.json").exists() {
-                    return (0, Err(CompileAndRunError::SuiteNotElm));
+                    return before_run(0, CompileAndRunError::SuiteNotElm);
                 }
 
                 let suite_config = match get_suite_config(&suite)
                     .map_err(CompileAndRunError::CannotGetSuiteConfig)
                 {
                     Ok(cfg) => cfg,
-                    Err(e) => return (0, Err(e)),
-                };
-
-                let request_unused_port_from_os = 0;
-                let url = match ("localhost", request_unused_port_from_os)
-                    .to_socket_addrs()
-                    .context("creating socket address")
-                    .and_then(|mut it| {
-                        it.next()
-                            .ok_or_else(|| anyhow::anyhow!("No socket addresses found"))
-                    }) {
-                    Ok(url) => url,
-                    Err(e) => return (0, Err(CompileAndRunError::Server(e))),
-                };
-
-                let server = suite_config
-                    .network
-                    .clone()
-                    .map(|network| Server::new(&server_pool, Protocol::Http, url, network));
-                let suite_config = match suite_config
-                    .make_ready(server.as_ref().map(|s| (s.url().to_string(), s.protocol())))
-                {
-                    Ok(suite_config) => suite_config,
-                    Err(e) => {
-                        return (
-                            0,
-                            // TODO(harry) better error here (invalid output.json file)
-                            Err(CompileAndRunError::Server(e)),
-                        );
-                    }
+                    Err(e) => return before_run(0, e),
                 };
 
                 let compile_failure_allowed =
@@ -662,73 +1168,152 @@ fn compile_and_run(
                             platform,
                         });
 
-                let retries = match compile(
-                    suite.as_ref(),
-                    out_dir.as_ref().join(format!("elm-{}.js", opt_level.id())),
-                    &compiler_lock,
-                    opt_level,
-                    &elm_compiler,
-                    &config,
-                ) {
+                let build_id = elm_compiler.build_id(opt_level);
+                let out_file = out_dir.as_ref().join(format!("elm-{build_id}.js"));
+                let cache_entry = cache.and_then(|cache| {
+                    cache
+                        .entry(suite.as_ref(), &elm_compiler, opt_level)
+                        .map_err(|e| debug!("Could not compute compile cache key: {}", e))
+                        .ok()
+                });
+                let compiled = match &cache_entry {
+                    Some(entry) if entry.load(&out_file) => (0, Ok(())),
+                    _ => compile(
+                        suite.as_ref(),
+                        &out_dir.as_ref().join(format!("build-{build_id}")),
+                        &out_file,
+                        elm_homes,
+                        opt_level,
+                        &elm_compiler,
+                        config,
+                    )
+                    .also(|(_, res)| {
+                        if let (Some(entry), Ok(())) = (&cache_entry, res) {
+                            if let Err(e) = entry.store(&out_file) {
+                                debug!("Could not cache compiled output: {}", e);
+                            }
+                        }
+                    }),
+                };
+                let retries = match compiled {
                     (r, Ok(())) => (r),
                     (r, Err(e)) => {
                         debug!("Compiler failure compiling {}", suite.as_ref().display());
-                        return (
+                        return before_run(
                             r,
-                            Err(CompileAndRunError::CompileFailure {
+                            CompileAndRunError::CompileFailure {
                                 allowed: compile_failure_allowed,
                                 reason: e,
-                            }),
+                            },
                         );
                     }
                 };
 
                 if compile_failure_allowed {
-                    return (retries, Err(CompileAndRunError::ExpectedCompileFailure));
+                    return before_run(retries, CompileAndRunError::ExpectedCompileFailure);
                 }
 
-                if suite_config.skip_run_if.is_met(&RunFailsIfAllFacts {
-                    opt_level,
-                    stdlib_variant: elm_compiler.stdlib_variant,
-                    platform,
-                }) {
-                    return (retries, Ok(()));
-                }
+                let run_flag_set = |flag_set: Option<&str>| {
+                    let facts = RunFailsIfAllFacts {
+                        opt_level,
+                        stdlib_variant: elm_compiler.stdlib_variant,
+                        platform,
+                        flag_set: flag_set.map(str::to_string),
+                    };
+                    let suite_config = suite_config
+                        .clone()
+                        .select_flag_set(flag_set)
+                        .apply_overrides(&facts);
+
+                    let request_unused_port_from_os = 0;
+                    let url = ("localhost", request_unused_port_from_os)
+                        .to_socket_addrs()
+                        .context("creating socket address")
+                        .and_then(|mut it| {
+                            it.next()
+                                .ok_or_else(|| anyhow::anyhow!("No socket addresses found"))
+                        })
+                        .map_err(CompileAndRunError::Server)?;
+
+                    let protocol = suite_config.network_protocol.unwrap_or(Protocol::Http);
+                    let servers: Vec<_> = suite_config
+                        .network
+                        .iter()
+                        .flat_map(NetworkConfig::origins)
+                        .map(|(origin, network)| {
+                            Server::new(&server_pool, protocol, url, origin, Arc::clone(network))
+                        })
+                        .collect();
+                    let websocket_server = suite_config.websocket.as_ref().map(|websocket| {
+                        WebSocketServer::new(&server_pool, protocol, url, Arc::clone(websocket))
+                    });
+                    let suite_config = suite_config
+                        .make_ready(&servers, websocket_server.as_ref())
+                        // TODO(harry) better error here (invalid output.json file)
+                        .map_err(CompileAndRunError::Server)?;
+
+                    if suite_config.skip_run_if.is_met(&facts) {
+                        return Ok(());
+                    }
 
-                let run_failure_required = suite_config.run_fails_if.is_met(&RunFailsIfAllFacts {
-                    opt_level,
-                    stdlib_variant: elm_compiler.stdlib_variant,
-                    platform,
-                });
+                    let run_failure_required = suite_config.run_fails_if.is_met(&facts);
 
-                debug!(
-                    "Runtime failure allowed? {:?}. Config: {:?}. Actual {:?}",
-                    run_failure_required, &suite_config.run_fails_if, elm_compiler.stdlib_variant
-                );
-
-                if let Err(e) = run(
-                    suite.as_ref(),
-                    out_dir.as_ref(),
-                    opt_level,
-                    &config,
-                    &suite_config,
-                ) {
-                    return (
-                        retries,
-                        Err(CompileAndRunError::RunFailure {
+                    debug!(
+                        "Runtime failure allowed? {:?}. Config: {:?}. Actual {:?}",
+                        run_failure_required,
+                        &suite_config.run_fails_if,
+                        elm_compiler.stdlib_variant
+                    );
+
+                    let run_result = run(
+                        suite.as_ref(),
+                        out_dir.as_ref(),
+                        &build_id,
+                        flag_set,
+                        config,
+                        &suite_config,
+                        bless,
+                    );
+                    // Unexpected requests are likely to be the cause of any run
+                    // time error so report them in preference.
+                    let mismatches: Box<[_]> = servers
+                        .iter()
+                        .filter_map(|s| s.check(run_result.is_ok()).err())
+                        .collect();
+                    let network_result = if mismatches.is_empty() {
+                        websocket_server
+                            .as_ref()
+                            .map_or(Ok(()), |s| s.check(run_result.is_ok()))
+                            .map_err(RunError::WebSocket)
+                    } else {
+                        Err(RunError::Network(mismatches))
+                    };
+                    network_result
+                        .and(run_result)
+                        .map_err(|e| CompileAndRunError::RunFailure {
                             allowed: run_failure_required,
                             reason: e,
-                        }),
-                    );
+                        })?;
+
+                    if run_failure_required {
+                        return Err(CompileAndRunError::ExpectedRunFailure);
+                    }
+                    Ok(())
                 };
 
-                if run_failure_required {
-                    return (retries, Err(CompileAndRunError::ExpectedRunFailure));
-                }
-                (retries, Ok(()))
+                suite_config
+                    .flag_sets()
+                    .into_iter()
+                    .map(|flag_set| {
+                        let res = run_flag_set(flag_set.as_deref());
+                        (flag_set, (retries, res))
+                    })
+                    .collect()
             })
             .unwrap();
-            ((elm_compiler, opt_level), res)
+            res.into_iter()
+                .map(|(flag_set, res)| ((elm_compiler.clone(), opt_level, flag_set), res))
+                .collect::<Vec<_>>()
         })
         .collect()
 }
@@ -744,12 +1329,17 @@ pub struct CompileAndRunResults<Ps> {
 
 pub enum SuitesError {
     ResolvingCompiler(DetectStdlibError),
+    PreparingCache(io::Error),
+    PreparingElmHome(io::Error),
+    /// There are no compilers or no optimisation levels to run suites with.
+    NothingToRun,
     // CompilerNotFound(which::Error),
     // CannotDetectStdlibVariant(DetectStdlibError),
 }
 
 #[allow(clippy::too_many_lines)]
 pub fn compile_and_run_suites<'a, Ps: AsRef<Path> + Send + Sync + 'a>(
+    suites_dir: &'a Path,
     suites: impl IntoParallelIterator<Item = Ps> + 'a,
     instructions: &'a super::cli::Instructions,
 ) -> Result<impl IntoParallelIterator<Item = CompileAndRunResults<Ps>> + 'a, SuitesError> {
@@ -768,10 +1358,14 @@ pub fn compile_and_run_suites<'a, Ps: AsRef<Path> + Send + Sync + 'a>(
     if !out_dir.exists() {
         let _ = fs::create_dir(&out_dir);
     }
-    let compiler_lock = Mutex::new(());
+    // Compilers are run from within the output directory so paths into it
+    // must not be relative.
+    let out_dir = out_dir.canonicalize().unwrap_or(out_dir);
+    let elm_homes =
+        ElmHomes::new(out_dir.join(".elm-homes")).map_err(SuitesError::PreparingElmHome)?;
     let prev_runs_failed = AtomicBool::new(false);
 
-    let elm_compilers = instructions
+    let mut elm_compilers = instructions
         .config
         .elm_compilers()
         .iter()
@@ -779,14 +1373,46 @@ pub fn compile_and_run_suites<'a, Ps: AsRef<Path> + Send + Sync + 'a>(
         .collect::<Result<Vec<_>, _>>()
         .map_err(SuitesError::ResolvingCompiler)?;
 
+    let (first_compiler, first_opt_level) = match (
+        elm_compilers.first(),
+        instructions.config.opt_levels().first(),
+    ) {
+        (Some(compiler), Some(opt_level)) => (compiler.clone(), opt_level),
+        _ => return Err(SuitesError::NothingToRun),
+    };
+
+    // Blessing rewrites the suite's expectations so only record the behaviour
+    // of the first compiler at the first optimisation level.
+    let opt_levels = if instructions.bless {
+        if elm_compilers.len() > 1 || instructions.config.opt_levels().len() > 1 {
+            eprintln!(
+                "Warning: blessing only records the behaviour of {first_compiler} in \
+                 {first_opt_level} optimisation mode, skipping the other compilers and \
+                 optimisation levels requested."
+            );
+        }
+        elm_compilers.truncate(1);
+        slice::from_ref(first_opt_level)
+    } else {
+        instructions.config.opt_levels()
+    };
+
+    let cache = instructions
+        .config
+        .cache_dir
+        .as_ref()
+        .map(|dir| CompileCache::new(dir.clone(), &elm_compilers, &elm_homes))
+        .transpose()
+        .map_err(SuitesError::PreparingCache)?;
+
     let scanner = move |suite: Ps| {
         if instructions.fail_fast && prev_runs_failed.load(Ordering::Relaxed) {
             None
         } else {
-            let sscce_out_dir = out_dir.join(suite.as_ref().file_name().expect("todo"));
+            let sscce_out_dir = out_dir.join(find_suites::out_dir_name(suites_dir, suite.as_ref()));
 
             if !sscce_out_dir.exists() {
-                let _ = fs::create_dir(&sscce_out_dir);
+                let _ = fs::create_dir_all(&sscce_out_dir);
             }
             if !sscce_out_dir.is_dir() {
                 // TODO(harry): handle this error better
@@ -795,10 +1421,7 @@ pub fn compile_and_run_suites<'a, Ps: AsRef<Path> + Send + Sync + 'a>(
                     sscce_out_dir,
                     errors: HashMap::new().also(|hm| {
                         hm.insert(
-                            (
-                                elm_compilers[0].clone(),
-                                instructions.config.opt_levels()[0],
-                            ),
+                            (first_compiler.clone(), *first_opt_level, None),
                             (0, Some(CompileAndRunError::OutDirIsNotDir)),
                         );
                     }),
@@ -808,33 +1431,31 @@ pub fn compile_and_run_suites<'a, Ps: AsRef<Path> + Send + Sync + 'a>(
             let errors = compile_and_run(
                 &suite,
                 &sscce_out_dir,
-                &compiler_lock,
-                iter_pairs(
-                    elm_compilers.clone(),
-                    instructions.config.opt_levels().par_iter().copied(),
-                ),
+                &elm_homes,
+                cache.as_ref(),
+                iter_pairs(elm_compilers.clone(), opt_levels.par_iter().copied()),
                 &instructions.config,
+                instructions.bless,
             )
             .into_iter()
-            .map(|(opt_level, (retries, res))| {
-                if let Err(CompileAndRunError::RunFailure { .. }) = res {
-                    if let Some(dir) = tmp_dir.lock().unwrap().take() {
-                        dir.into_path();
-                    }
-                } else {
-                    let _ = fs::remove_dir_all(&sscce_out_dir);
-                };
-                let failed = match res {
-                    Err(CompileAndRunError::CompileFailure { allowed: true, .. })
-                    | Err(CompileAndRunError::RunFailure { allowed: true, .. })
-                    | Ok(_) => false,
-                    Err(_) => true,
-                };
-                // Never clear `prev_run_failed`, only set it.
-                prev_runs_failed.fetch_or(failed, Ordering::Relaxed);
-                (opt_level, (retries, res.err()))
-            })
+            .map(|(opt_level, (retries, res))| (opt_level, (retries, res.err())))
             .collect::<HashMap<_, _>>();
+            let outcomes = errors
+                .values()
+                .map(|(_, error)| Outcome::of(error.as_ref()))
+                .collect::<Vec<_>>();
+            // Keep the built files (including the compiler's `elm-stuff`) for
+            // anything that did not go as expected so that it can be debugged.
+            if outcomes.iter().all(|&outcome| outcome == Outcome::Success) {
+                let _ = fs::remove_dir_all(&sscce_out_dir);
+            } else if let Some(dir) = tmp_dir.lock().unwrap().take() {
+                dir.into_path();
+            }
+            let failed = outcomes
+                .iter()
+                .any(|&outcome| matches!(outcome, Outcome::UnexpectedSuccess | Outcome::Failure));
+            // Never clear `prev_run_failed`, only set it.
+            prev_runs_failed.fetch_or(failed, Ordering::Relaxed);
             Some(CompileAndRunResults {
                 suite,
                 sscce_out_dir,
@@ -844,56 +1465,3 @@ pub fn compile_and_run_suites<'a, Ps: AsRef<Path> + Send + Sync + 'a>(
     };
     suites.into_par_iter().map(scanner).while_some().apply(Ok)
 }
-
-struct Server<'pool> {
-    id: ServerId<'pool>,
-    protocol: Protocol,
-}
-
-impl<'pool> Server<'pool> {
-    fn new(
-        server_pool: &'pool ServerPool,
-        protocol: Protocol,
-        a: SocketAddr,
-        network_data: Arc<[NetworkItem]>,
-    ) -> Self {
-        #[allow(clippy::mutex_atomic)]
-        let index = Arc::new(Mutex::new(0));
-        Self {
-            id: server_pool.start(
-                warp::path::full()
-                    .and(warp::method())
-                    .map(move |name: warp::path::FullPath, method| {
-                        let mut i = index.lock().unwrap();
-                        match &network_data[*i] {
-                            NetworkItem {
-                                request: Request::Get { url },
-                                response,
-                            } => {
-                                debug!("Request with url {} and method GET", name.as_str());
-                                assert!(method == warp::http::Method::GET);
-                                assert!(name.as_str() == url);
-                                *i += 1;
-                                response.0.clone()
-                            }
-                        }
-                    })
-                    .with(warp::log("api")),
-                protocol,
-                a,
-            ),
-            protocol,
-        }
-    }
-
-    fn url(&self) -> SocketAddr {
-        self.id.url
-    }
-
-    fn protocol(&self) -> String {
-        match self.protocol {
-            Protocol::Http => "http://".into(),
-            Protocol::Https => "https://".into(),
-        }
-    }
-}
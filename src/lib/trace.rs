@@ -0,0 +1,94 @@
+use core::fmt;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Something the SSCCE did whilst the harness was running it.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum TraceEvent {
+    /// The SSCCE sent `data` out of a command port.
+    Command {
+        port: String,
+        #[serde(default)]
+        data: Value,
+    },
+    /// The harness sent `data` into a subscription port.
+    Subscription {
+        port: String,
+        #[serde(default)]
+        data: Value,
+    },
+    /// The SSCCE logged a message (using `Debug.log`).
+    Log { message: String },
+    /// The SSCCE made an HTTP request.
+    Request { method: String, url: String },
+}
+
+impl TraceEvent {
+    fn is_port_event(&self) -> bool {
+        matches!(
+            self,
+            TraceEvent::Command { .. } | TraceEvent::Subscription { .. }
+        )
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::Command { port, data } => write!(f, "command {port} ({data})"),
+            TraceEvent::Subscription { port, data } => {
+                write!(f, "subscription {port} ({data})")
+            }
+            TraceEvent::Log { message } => write!(f, "log {message:?}"),
+            TraceEvent::Request { method, url } => write!(f, "request {method} {url}"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct TimedTraceEvent {
+    /// Milliseconds since the harness started.
+    pub time: f64,
+    #[serde(flatten)]
+    pub event: TraceEvent,
+}
+
+/// What the SSCCE did during a run, as recorded by the harness.
+///
+/// The harness appends events to the trace file as they happen so that the
+/// trace survives the SSCCE being killed.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Trace {
+    pub expected_port_events: usize,
+    pub events: Box<[TimedTraceEvent]>,
+}
+
+impl Trace {
+    /// Read the trace written by the harness, ignoring a final event that
+    /// was only partly written.
+    pub fn read(path: &Path, expected_port_events: usize) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let events = contents
+            .lines()
+            .map_while(|line| serde_json::from_str(line).ok())
+            .collect();
+        Ok(Self {
+            expected_port_events,
+            events,
+        })
+    }
+
+    pub fn port_events(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|TimedTraceEvent { event, .. }| event.is_port_event())
+            .count()
+    }
+}
@@ -0,0 +1,212 @@
+use super::server_pool::Protocol;
+use super::server_pool::ServerId;
+use super::server_pool::ServerPool;
+use core::fmt;
+use futures::{SinkExt, StreamExt};
+use log::debug;
+use serde::Deserialize;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use warp::ws::{Message, WebSocket};
+use warp::Filter;
+
+/// One step of the conversation between the SSCCE and the mock WebSocket
+/// server.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum Step {
+    /// The server pushes this message to the SSCCE.
+    Push(String),
+    /// The SSCCE must send this message to the server.
+    Expect(String),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Push(message) => write!(f, "push {message:?}"),
+            Step::Expect(message) => write!(f, "expect {message:?}"),
+        }
+    }
+}
+
+/// The `websocket` key of a suite's config.
+///
+/// The harness connects to the mock server and bridges the connection to a
+/// pair of ports: messages sent to `send-port` are sent to the server and
+/// messages from the server are sent to `receive-port`. Once every step of
+/// the script has happened the server closes the connection and any further
+/// messages from the SSCCE are mismatches.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WebSocketConfig {
+    send_port: String,
+    receive_port: String,
+    script: Box<[Step]>,
+}
+
+/// A way in which the conversation with the SSCCE differed from the script.
+///
+/// Steps are indexed by the order in which they are listed in the script.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum WebSocketMismatch {
+    /// The SSCCE sent `actual` when the script expected `expected`.
+    Unexpected {
+        index: usize,
+        expected: String,
+        actual: String,
+    },
+    /// The conversation ended before step `index` of the script.
+    Incomplete { index: usize, expected: Step },
+    /// The SSCCE opened more than one connection.
+    ExtraConnection,
+    /// The SSCCE sent `actual` after every step of the script had happened.
+    AfterScript { actual: String },
+}
+
+#[derive(Debug, Default)]
+struct WebSocketState {
+    connections: usize,
+    /// The number of steps of the script that have happened.
+    completed: usize,
+    mismatches: Vec<WebSocketMismatch>,
+}
+
+pub(crate) struct WebSocketServer<'pool> {
+    id: ServerId<'pool>,
+    protocol: Protocol,
+    config: Arc<WebSocketConfig>,
+    state: Arc<Mutex<WebSocketState>>,
+}
+
+impl<'pool> WebSocketServer<'pool> {
+    pub fn new(
+        server_pool: &'pool ServerPool,
+        protocol: Protocol,
+        a: SocketAddr,
+        config: Arc<WebSocketConfig>,
+    ) -> Self {
+        let state = Arc::new(Mutex::new(WebSocketState::default()));
+        let handler_state = Arc::clone(&state);
+        let handler_config = Arc::clone(&config);
+        Self {
+            id: server_pool.start(
+                warp::ws()
+                    .map(move |ws: warp::ws::Ws| {
+                        let state = Arc::clone(&handler_state);
+                        let config = Arc::clone(&handler_config);
+                        ws.on_upgrade(move |socket| converse(socket, config, state))
+                    })
+                    .with(warp::log("websocket")),
+                protocol,
+                a,
+            ),
+            protocol,
+            config,
+            state,
+        }
+    }
+
+    pub fn url(&self) -> String {
+        match self.protocol {
+            Protocol::Http => format!("ws://{}", self.id.url),
+            Protocol::Https => format!("wss://{}", self.id.url),
+        }
+    }
+
+    /// Check that the conversation with the SSCCE followed the script.
+    ///
+    /// An incomplete conversation is only reported if `finished` is true
+    /// (i.e. the SSCCE ran to completion).
+    pub fn check(&self, finished: bool) -> Result<(), Box<[WebSocketMismatch]>> {
+        let state = self.state.lock().unwrap();
+        let mut mismatches = state.mismatches.clone();
+        if finished && mismatches.is_empty() {
+            if let Some(expected) = self.config.script.get(state.completed) {
+                mismatches.push(WebSocketMismatch::Incomplete {
+                    index: state.completed,
+                    expected: expected.clone(),
+                });
+            }
+        }
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches.into_boxed_slice())
+        }
+    }
+}
+
+/// The next text message from the SSCCE, or `None` if the connection closed.
+async fn next_message(socket: &mut WebSocket) -> Option<String> {
+    while let Some(Ok(message)) = socket.next().await {
+        if message.is_close() {
+            return None;
+        }
+        if message.is_text() || message.is_binary() {
+            return Some(String::from_utf8_lossy(message.as_bytes()).into_owned());
+        }
+    }
+    None
+}
+
+async fn converse(
+    mut socket: WebSocket,
+    config: Arc<WebSocketConfig>,
+    state: Arc<Mutex<WebSocketState>>,
+) {
+    {
+        let mut state = state.lock().unwrap();
+        state.connections += 1;
+        if state.connections > 1 {
+            state.mismatches.push(WebSocketMismatch::ExtraConnection);
+            return;
+        }
+    }
+    for (index, step) in config.script.iter().enumerate() {
+        debug!("WebSocket step {}: {}", index, step);
+        match step {
+            Step::Push(message) => {
+                if socket.send(Message::text(message.clone())).await.is_err() {
+                    return;
+                }
+            }
+            Step::Expect(expected) => match next_message(&mut socket).await {
+                Some(actual) if actual == *expected => {}
+                Some(actual) => {
+                    state
+                        .lock()
+                        .unwrap()
+                        .mismatches
+                        .push(WebSocketMismatch::Unexpected {
+                            index,
+                            expected: expected.clone(),
+                            actual,
+                        });
+                    break;
+                }
+                None => return,
+            },
+        }
+        state.lock().unwrap().completed = index + 1;
+    }
+    let script_finished = state.lock().unwrap().completed == config.script.len();
+    if !script_finished {
+        let _ = socket.close().await;
+        return;
+    }
+    if socket.send(Message::close()).await.is_err() {
+        return;
+    }
+    // Messages sent before the SSCCE sees the close arrive ahead of its reply.
+    while let Some(actual) = next_message(&mut socket).await {
+        state
+            .lock()
+            .unwrap()
+            .mismatches
+            .push(WebSocketMismatch::AfterScript { actual });
+    }
+}
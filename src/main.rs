@@ -4,17 +4,16 @@
 
 mod lib;
 
-use colored::Colorize;
 use lib::cli;
 use lib::formatting;
+use lib::json_report;
+use lib::junit;
 use lib::suite;
 use rayon::prelude::*;
-use std::io::Write;
-use std::{collections::HashSet, fs, process};
+use std::io::{self, Write};
+use std::{fs, process};
 use std::{num::NonZeroI32, path::Path};
 
-const WELCOME_MESSAGE: &str = "Elm Torture - stress tests for an elm compiler";
-
 const CATCH_ALL_ERROR_CODE: i32 = 0x28;
 
 #[allow(clippy::enum_glob_use)]
@@ -48,130 +47,33 @@ fn get_exit_code(err: &suite::CompileAndRunError) -> i32 {
     }
 }
 
-fn sscce_result_printer(
-    suite::CompileAndRunResults {
-        suite,
-        sscce_out_dir,
-        errors,
-    }: &suite::CompileAndRunResults<impl AsRef<Path>>,
-) {
-    let errors_to_print = errors.iter().filter_map(|(ol, (retries, me))| match me {
-        Some(suite::CompileAndRunError::RunFailure { allowed, .. })
-        | Some(suite::CompileAndRunError::CompileFailure { allowed, .. })
-            if *allowed =>
-        {
-            None
-        }
-        e => e.as_ref().map(|ee| (ol, (retries, ee))),
-    });
-    for ((elm_compiler, opt_level), (retries, e)) in errors_to_print {
-        println!(
-            "{} compiling with {} in {} optimisation mode\n{}",
-            suite.as_ref().display().to_string().black().on_white(),
-            elm_compiler.to_string().black().on_white(),
-            opt_level.to_string().black().on_white(),
-            indented::indented(formatting::compile_and_run_error(
-                e,
-                suite,
-                &sscce_out_dir,
-                *retries
-            ))
-        );
-    }
-}
-
-#[allow(clippy::too_many_lines)]
-fn run_suites(
-    suites: &[impl AsRef<Path> + Sync],
+fn run_suites<P: AsRef<Path> + Sync>(
+    suites_dir: &Path,
+    suites: &[P],
     instructions: &cli::Instructions,
 ) -> Option<NonZeroI32> {
     assert!(!suites.is_empty());
-    println!(
-        "{}
+    let reporter = instructions.reporter.reporter::<&P>();
+    reporter.started(&suites.iter().map(AsRef::as_ref).collect::<Vec<_>>());
 
-Running the following {} SSCCE{}:
-{}
-",
-        WELCOME_MESSAGE,
-        suites.len(),
-        if suites.len() == 1 { "" } else { "s" },
-        indented::indented(formatting::easy_format(|f| {
-            for path in suites.iter() {
-                writeln!(f, "{}", path.as_ref().display())?
-            }
-            Ok(())
-        }))
-    );
-
-    match suite::compile_and_run_suites(suites.par_iter(), instructions) {
+    match suite::compile_and_run_suites(suites_dir, suites.par_iter(), instructions) {
         Ok(res_iter) => {
             let suite_results: Vec<_> = res_iter
                 .into_par_iter()
-                .inspect(sscce_result_printer)
+                .inspect(|result| reporter.suite_finished(result))
                 .collect();
 
-            println!(
-                "
-elm-torture has run the following {} SSCCE{}:
-{}
-",
-                suites.len(),
-                if suites.len() == 1 { "" } else { "s" },
-                indented::indented(formatting::easy_format(|f| {
-                    let mut opt_levels_of_interest = HashSet::new();
-                    loop {
-                        let mut current_opt_level = None;
-                        for suite::CompileAndRunResults { suite, errors, .. } in &suite_results {
-                            use suite::CompileAndRunError;
-                            for (sscce_run_type, (_, possible_error)) in errors.iter() {
-                                let (compiler, run_opt_level) = sscce_run_type;
-                                let should_print = if let Some(ol) = current_opt_level {
-                                    ol == sscce_run_type
-                                } else if opt_levels_of_interest.contains(sscce_run_type) {
-                                    false
-                                } else {
-                                    current_opt_level = Some(sscce_run_type);
-                                    opt_levels_of_interest.insert(sscce_run_type);
-                                    writeln!(
-                                        f,
-                                        "Compiling with {} in {} optimisation mode",
-                                        compiler.to_string().black().on_white(),
-                                        run_opt_level.to_string().black().on_white(),
-                                    )?;
-                                    true
-                                };
-                                if should_print {
-                                    writeln_indented!(
-                                f,
-                                "{} ({})",
-                                suite.as_ref().display(),
-                                match possible_error {
-                                    Some(CompileAndRunError::RunFailure {
-                                        allowed: true, ..
-                                    }) => "allowed run failure".yellow(),
-                                    Some(CompileAndRunError::CompileFailure {
-                                        allowed: true,
-                                        ..
-                                    }) => "allowed compile failure".yellow(),
-                                    Some(CompileAndRunError::ExpectedCompileFailure) =>
-                                        "success when elm-torture expected a compile time failure"
-                                            .red(),
-                                    Some(CompileAndRunError::ExpectedRunFailure) =>
-                                        "success when elm-torture expected a run time failure".red(),
-                                    Some(_) => "failure".red(),
-                                    None => "success".green(),
-                                }
-                            )?
-                                }
-                            }
-                        }
-                        if current_opt_level.is_none() {
-                            break;
-                        }
-                    }
-                    Ok(())
-                }))
-            );
+            reporter.finished(&suite_results);
+            if let Some(junit_file) = &instructions.junit {
+                let file = fs::File::create(junit_file).expect("could not create junit report");
+                junit::write_report(&suite_results, io::BufWriter::new(file))
+                    .expect("could not write junit report");
+            }
+            if let Some(json_file) = &instructions.report_json {
+                let file = fs::File::create(json_file).expect("could not create json report");
+                json_report::write_report(&suite_results, io::BufWriter::new(file))
+                    .expect("could not write json report");
+            }
             let code = suite_results
                 .iter()
                 .flat_map(|suite::CompileAndRunResults { errors, .. }| {
@@ -197,13 +99,15 @@ fn run_app(instructions: cli::Instructions) -> Option<NonZeroI32> {
             writeln!(&mut file).expect("could not write to file config");
             None
         }
-        cli::Task::RunSuites(ref suite_dir) => match lib::find_suites::find_suites(&suite_dir) {
-            Ok(suites) => run_suites(&suites, &instructions),
-            Err(ref err) => {
-                eprint!("{}", formatting::find_suite_error(err, suite_dir));
-                NonZeroI32::new(0x28)
+        cli::Task::RunSuites(ref suite_dir) => {
+            match lib::find_suites::find_suites(suite_dir, &instructions.selection) {
+                Ok(suites) => run_suites(suite_dir, &suites, &instructions),
+                Err(ref err) => {
+                    eprint!("{}", formatting::find_suite_error(err, suite_dir));
+                    NonZeroI32::new(0x28)
+                }
             }
-        },
+        }
     }
 }
 
@@ -0,0 +1,20 @@
+port module Main exposing (main)
+
+{-| Elm does not specify the order in which the commands in a batch reach
+different ports.
+-}
+
+
+port left : String -> Cmd never
+
+
+port right : String -> Cmd never
+
+
+main : Program () () Never
+main =
+    Platform.worker
+        { init = \_ -> ( (), Cmd.batch [ left "from left", right "from right" ] )
+        , update = \_ model -> ( model, Cmd.none )
+        , subscriptions = \_ -> Sub.none
+        }
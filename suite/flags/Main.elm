@@ -0,0 +1,25 @@
+module Main exposing (main)
+
+{-| Run with each of the flag sets in output.json.
+-}
+
+import Util.Cmds
+
+
+type alias Flags =
+    { greeting : String
+    , times : Int
+    }
+
+
+main : Program Flags () Never
+main =
+    Platform.worker
+        { init =
+            \{ greeting, times } ->
+                ( ()
+                , Util.Cmds.write (String.join " " (List.repeat times greeting))
+                )
+        , update = \_ model -> ( model, Cmd.none )
+        , subscriptions = \_ -> Sub.none
+        }
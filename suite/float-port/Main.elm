@@ -0,0 +1,16 @@
+port module Main exposing (main)
+
+{-| Floats sent out of a port need only be close to the expected value.
+-}
+
+
+port float : Float -> Cmd never
+
+
+main : Program () () Never
+main =
+    Platform.worker
+        { init = \_ -> ( (), float (1 / 3) )
+        , update = \_ model -> ( model, Cmd.none )
+        , subscriptions = \_ -> Sub.none
+        }
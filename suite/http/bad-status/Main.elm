@@ -0,0 +1,34 @@
+module Main exposing (main)
+
+import Http
+import Platform
+import Util.Http
+import Util.Programs exposing (SuiteFlags)
+
+
+deleteBook : String -> String -> Cmd (Result Http.Error String)
+deleteBook protocol url =
+    Http.request
+        { method = "DELETE"
+        , headers = [ Http.header "X-Reason" "out of print" ]
+        , url = protocol ++ url ++ "/books/1"
+        , body = Http.emptyBody
+        , expect = Http.expectString identity
+        , timeout = Nothing
+        , tracker = Nothing
+        }
+
+
+update msg () =
+    ( ()
+    , Util.Http.actOnHttpResponse msg
+    )
+
+
+main : Platform.Program (SuiteFlags {}) () (Result Http.Error String)
+main =
+    Platform.worker
+        { init = \{ suite } -> ( (), deleteBook suite.protocol suite.url )
+        , update = update
+        , subscriptions = \() -> Sub.none
+        }
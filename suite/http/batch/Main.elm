@@ -0,0 +1,49 @@
+module Main exposing (main)
+
+import Http
+import Platform
+import Util.Cmds
+import Util.Http
+import Util.Programs exposing (SuiteFlags)
+
+
+getFruit : String -> String -> String -> Cmd (Result Http.Error String)
+getFruit protocol url fruit =
+    Http.get
+        { url = protocol ++ url ++ "/fruit/" ++ fruit
+        , expect = Http.expectString identity
+        }
+
+
+update : Result Http.Error String -> List String -> ( List String, Cmd never )
+update msg fruits =
+    case msg of
+        Ok fruit ->
+            let
+                newFruits =
+                    fruit :: fruits
+            in
+            ( newFruits
+            , if List.length newFruits == 3 then
+                Util.Cmds.write (String.join "," (List.sort newFruits))
+
+              else
+                Cmd.none
+            )
+
+        Err _ ->
+            ( fruits, Util.Http.actOnHttpResponse msg )
+
+
+main : Platform.Program (SuiteFlags {}) (List String) (Result Http.Error String)
+main =
+    Platform.worker
+        { init =
+            \{ suite } ->
+                ( []
+                , Cmd.batch
+                    (List.map (getFruit suite.protocol suite.url) [ "apple", "banana", "cherry" ])
+                )
+        , update = update
+        , subscriptions = \_ -> Sub.none
+        }
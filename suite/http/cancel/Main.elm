@@ -0,0 +1,56 @@
+module Main exposing (main)
+
+import Http
+import Platform
+import Util.Cmds
+import Util.Http
+import Util.Programs exposing (SuiteFlags)
+
+
+type Msg
+    = GotBody (Result Http.Error String)
+    | GotProgress Http.Progress
+
+
+download : String -> String -> Cmd Msg
+download protocol url =
+    Http.request
+        { method = "GET"
+        , headers = []
+        , url = protocol ++ url ++ "/download"
+        , body = Http.emptyBody
+        , expect = Http.expectString GotBody
+        , timeout = Nothing
+        , tracker = Just "download"
+        }
+
+
+update : Msg -> Bool -> ( Bool, Cmd Msg )
+update msg cancelled =
+    case msg of
+        GotProgress (Http.Receiving { received }) ->
+            if cancelled then
+                ( cancelled, Util.Cmds.error [ "Progress after cancelling" ] )
+
+            else
+                ( True
+                , Cmd.batch
+                    [ Http.cancel "download"
+                    , Util.Cmds.write ("cancelled after " ++ String.fromInt received)
+                    ]
+                )
+
+        GotProgress (Http.Sending _) ->
+            ( cancelled, Cmd.none )
+
+        GotBody result ->
+            ( cancelled, Util.Http.actOnHttpResponse result )
+
+
+main : Platform.Program (SuiteFlags {}) Bool Msg
+main =
+    Platform.worker
+        { init = \{ suite } -> ( False, download suite.protocol suite.url )
+        , update = update
+        , subscriptions = \_ -> Http.track "download" GotProgress
+        }
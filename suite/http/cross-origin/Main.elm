@@ -0,0 +1,62 @@
+module Main exposing (main)
+
+import Http
+import Platform
+import Util.Cmds
+import Util.Http
+
+
+type alias Origin =
+    { url : String
+    , protocol : String
+    }
+
+
+type alias Flags =
+    { suite :
+        { origins :
+            { api : Origin
+            , cdn : Origin
+            }
+        }
+    }
+
+
+type Msg
+    = GotAsset (Result Http.Error String)
+    | GotContents (Result Http.Error String)
+
+
+get : Origin -> String -> (Result Http.Error String -> Msg) -> Cmd Msg
+get { protocol, url } path toMsg =
+    Http.get
+        { url = protocol ++ url ++ path
+        , expect = Http.expectString toMsg
+        }
+
+
+update : Msg -> Origin -> ( Origin, Cmd Msg )
+update msg cdn =
+    case msg of
+        GotAsset (Ok asset) ->
+            ( cdn, get cdn ("/" ++ asset) GotContents )
+
+        GotContents (Ok contents) ->
+            ( cdn, Util.Cmds.write contents )
+
+        GotAsset result ->
+            ( cdn, Util.Http.actOnHttpResponse result )
+
+        GotContents result ->
+            ( cdn, Util.Http.actOnHttpResponse result )
+
+
+main : Platform.Program Flags Origin Msg
+main =
+    Platform.worker
+        { init =
+            \{ suite } ->
+                ( suite.origins.cdn, get suite.origins.api "/asset" GotAsset )
+        , update = update
+        , subscriptions = \_ -> Sub.none
+        }
@@ -0,0 +1,39 @@
+module Main exposing (main)
+
+import Http
+import Platform
+import Util.Cmds
+import Util.Http
+import Util.Programs exposing (SuiteFlags)
+
+
+getFruit : String -> String -> String -> Cmd (Result Http.Error String)
+getFruit protocol url fruit =
+    Http.get
+        { url = protocol ++ url ++ "/fruit/" ++ fruit
+        , expect = Http.expectString identity
+        }
+
+
+update : Result Http.Error String -> () -> ( (), Cmd never )
+update msg () =
+    case msg of
+        Ok fruit ->
+            ( (), Util.Cmds.write fruit )
+
+        Err _ ->
+            ( (), Util.Http.actOnHttpResponse msg )
+
+
+main : Platform.Program (SuiteFlags {}) () (Result Http.Error String)
+main =
+    Platform.worker
+        { init =
+            \{ suite } ->
+                ( ()
+                , Cmd.batch
+                    (List.map (getFruit suite.protocol suite.url) [ "apple", "banana" ])
+                )
+        , update = update
+        , subscriptions = \_ -> Sub.none
+        }
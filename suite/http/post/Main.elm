@@ -0,0 +1,30 @@
+module Main exposing (main)
+
+import Http
+import Platform
+import Util.Http
+import Util.Programs exposing (SuiteFlags)
+
+
+postBook : String -> String -> Cmd (Result Http.Error String)
+postBook protocol url =
+    Http.post
+        { url = protocol ++ url ++ "/books"
+        , body = Http.stringBody "text/plain" "The Hobbit"
+        , expect = Http.expectString identity
+        }
+
+
+update msg () =
+    ( ()
+    , Util.Http.actOnHttpResponse msg
+    )
+
+
+main : Platform.Program (SuiteFlags {}) () (Result Http.Error String)
+main =
+    Platform.worker
+        { init = \{ suite } -> ( (), postBook suite.protocol suite.url )
+        , update = update
+        , subscriptions = \() -> Sub.none
+        }
@@ -0,0 +1,59 @@
+module Main exposing (main)
+
+import Dict
+import Http
+import Platform
+import Util.Http
+import Util.Programs exposing (SuiteFlags)
+
+
+describeResponse : Http.Response String -> Result Http.Error String
+describeResponse response =
+    case response of
+        Http.BadUrl_ url ->
+            Err (Http.BadUrl url)
+
+        Http.Timeout_ ->
+            Err Http.Timeout
+
+        Http.NetworkError_ ->
+            Err Http.NetworkError
+
+        Http.BadStatus_ metadata body ->
+            Ok (describe "bad status" metadata body)
+
+        Http.GoodStatus_ metadata body ->
+            Ok (describe "good status" metadata body)
+
+
+describe : String -> Http.Metadata -> String -> String
+describe kind metadata body =
+    String.join " "
+        [ kind
+        , String.fromInt metadata.statusCode
+        , Dict.get "x-book-count" metadata.headers |> Maybe.withDefault "(missing)"
+        , body
+        ]
+
+
+getBooks : String -> String -> Cmd (Result Http.Error String)
+getBooks protocol url =
+    Http.get
+        { url = protocol ++ url ++ "/books"
+        , expect = Http.expectStringResponse identity describeResponse
+        }
+
+
+update msg () =
+    ( ()
+    , Util.Http.actOnHttpResponse msg
+    )
+
+
+main : Platform.Program (SuiteFlags {}) () (Result Http.Error String)
+main =
+    Platform.worker
+        { init = \{ suite } -> ( (), getBooks suite.protocol suite.url )
+        , update = update
+        , subscriptions = \() -> Sub.none
+        }
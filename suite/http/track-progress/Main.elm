@@ -0,0 +1,54 @@
+module Main exposing (main)
+
+import Http
+import Platform
+import Util.Cmds
+import Util.Http
+import Util.Programs exposing (SuiteFlags)
+
+
+type Msg
+    = GotBody (Result Http.Error String)
+    | GotProgress Http.Progress
+
+
+download : String -> String -> Cmd Msg
+download protocol url =
+    Http.request
+        { method = "GET"
+        , headers = []
+        , url = protocol ++ url ++ "/download"
+        , body = Http.emptyBody
+        , expect = Http.expectString GotBody
+        , timeout = Nothing
+        , tracker = Just "download"
+        }
+
+
+update : Msg -> () -> ( (), Cmd Msg )
+update msg () =
+    case msg of
+        GotProgress (Http.Receiving { received, size }) ->
+            ( ()
+            , Util.Cmds.write
+                ("received "
+                    ++ String.fromInt received
+                    ++ " of "
+                    ++ (size |> Maybe.map String.fromInt |> Maybe.withDefault "unknown")
+                )
+            )
+
+        GotProgress (Http.Sending _) ->
+            ( (), Cmd.none )
+
+        GotBody result ->
+            ( (), Util.Http.actOnHttpResponse result )
+
+
+main : Platform.Program (SuiteFlags {}) () Msg
+main =
+    Platform.worker
+        { init = \{ suite } -> ( (), download suite.protocol suite.url )
+        , update = update
+        , subscriptions = \() -> Http.track "download" GotProgress
+        }
@@ -0,0 +1,21 @@
+module Main exposing (main)
+
+{-| Write the mode given in the flags, which output.json overrides when the
+suite is compiled with `--optimize`.
+-}
+
+import Util.Cmds
+
+
+type alias Flags =
+    { mode : String
+    }
+
+
+main : Program Flags () Never
+main =
+    Platform.worker
+        { init = \{ mode } -> ( (), Util.Cmds.write mode )
+        , update = \_ model -> ( model, Cmd.none )
+        , subscriptions = \_ -> Sub.none
+        }
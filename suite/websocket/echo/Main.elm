@@ -0,0 +1,33 @@
+port module Main exposing (main)
+
+import Platform
+import Util.Cmds
+
+
+port websocketSend : String -> Cmd never
+
+
+port websocketReceive : (String -> msg) -> Sub msg
+
+
+update : String -> () -> ( (), Cmd never )
+update message () =
+    ( ()
+    , Cmd.batch
+        [ websocketSend ("echo: " ++ message)
+        , if message == "goodbye" then
+            Util.Cmds.write "done"
+
+          else
+            Cmd.none
+        ]
+    )
+
+
+main : Platform.Program () () String
+main =
+    Platform.worker
+        { init = \() -> ( (), Cmd.none )
+        , update = update
+        , subscriptions = \() -> websocketReceive identity
+        }